@@ -0,0 +1,43 @@
+use crate::reng::types::*;
+use crate::world::{Ship, ShipParams, Resource};
+use strum::IntoEnumIterator;
+use super::types::Instance2D;
+
+//Hud elements are laid out in screen space, from -aspect to aspect horizontally and -1 to 1 vertically.
+pub fn rect(text_coords : GLvec4, center : (f32, f32), size : (f32, f32), tint : GLvec4) -> Instance2D {
+	let mut instance = Instance2D::default();
+	instance.color_tint = tint;
+	instance.texture_coords = text_coords;
+	instance.translate = GLvec2(center.0, center.1);
+	instance.scale = GLvec2(size.0 / 2.0, size.1 / 2.0);
+	instance
+}
+
+//Moves hud elements from screen space into the world, so they stay put under a camera at `camera` showing `zoom` units above and below it.
+pub fn to_world(instances : &mut [Instance2D], camera : (f32, f32), zoom : f32) {
+	for instance in instances {
+		instance.translate = GLvec2(camera.0 + instance.translate.0 * zoom, camera.1 + instance.translate.1 * zoom);
+		instance.scale = GLvec2(instance.scale.0 * zoom, instance.scale.1 * zoom);
+	}
+}
+
+//A bar filled from the left, drawn over a dim backing of the same size.
+pub fn bar(output : &mut Vec<Instance2D>, text_coords : GLvec4, left : (f32, f32), size : (f32, f32), fraction : f32, tint : GLvec4) {
+	let fraction = fraction.max(0.0).min(1.0);
+	output.push(rect(text_coords, (left.0 + size.0 / 2.0, left.1), size, GLvec4(0.0, 0.0, 0.0, 0.5)));
+	output.push(rect(text_coords, (left.0 + size.0 * fraction / 2.0, left.1), (size.0 * fraction, size.1), tint));
+}
+
+pub fn death_screen(output : &mut Vec<Instance2D>, text_coords : GLvec4, aspect : f32, ship : &Ship, params : &ShipParams) {
+	output.push(rect(text_coords, (0.0, 0.0), (2.0 * aspect, 2.0), GLvec4(0.25, 0.0, 0.0, 0.4)));
+	let remaining = if params.respawn_delay > 0.0 { ship.respawn_timer / params.respawn_delay } else { 0.0 };
+	bar(output, text_coords, (-0.5, -0.2), (1.0, 0.05), remaining, GLvec4(0.9, 0.2, 0.2, 1.0));
+}
+//One bar per resource in the bottom left corner, coloured like its pickups.
+pub fn inventory(output : &mut Vec<Instance2D>, text_coords : GLvec4, aspect : f32, ship : &Ship, params : &ShipParams) {
+	for (row, resource) in Resource::iter().enumerate() {
+		let capacity = resource.capacity(params);
+		let fraction = if capacity > 0.0 { ship.inventory.get(resource) / capacity } else { 0.0 };
+		bar(output, text_coords, (-aspect + 0.05, -0.92 + row as f32 * 0.06), (0.4, 0.04), fraction, resource.tint());
+	}
+}
@@ -1,23 +1,62 @@
 pub mod types;
 pub mod state;
+pub mod sheet;
+mod hud;
 
 use super::utils;
+use super::comms;
 use std::net;
 
-pub fn client(address : &str) {
+fn server_address(address : &str) -> net::SocketAddr {
 	use std::str::FromStr;
 
-	let server_addr;
 	if let Ok(addr) = net::SocketAddr::from_str(address) {
-		server_addr = addr;
+		addr
 	} else if let Ok(addr) = net::IpAddr::from_str(address) {
-		server_addr = net::SocketAddr::new(addr, utils::SERVER_PORT);
+		net::SocketAddr::new(addr, utils::SERVER_PORT)
 	} else {
-		server_addr = net::SocketAddr::new("127.0.0.1".parse().unwrap(), utils::SERVER_PORT);
+		net::SocketAddr::new("127.0.0.1".parse().unwrap(), utils::SERVER_PORT)
 	}
+}
+
+fn request_status(query_addr : net::SocketAddr) -> Result<comms::ServerStatus, Box<dyn std::error::Error>> {
+	let socket = net::UdpSocket::bind("0.0.0.0:0")?;
+	socket.set_read_timeout(Some(std::time::Duration::from_secs(2)))?;
+	socket.send_to(comms::QUERY_REQUEST, query_addr)?;
+
+	let mut buff = [0u8; 4096];
+	let (n, _) = socket.recv_from(&mut buff)?;
+	Ok(bincode::deserialize(&buff[..n])?)
+}
+
+pub fn query(address : &str, json : bool) {
+	let server_addr = server_address(address);
+	let query_addr = net::SocketAddr::new(server_addr.ip(), server_addr.port() + utils::QUERY_PORT - utils::SERVER_PORT);
+
+	match request_status(query_addr) {
+		Ok(status) if json => println!("{}", serde_json::to_string(&status).unwrap()),
+		Ok(status) => {
+			println!("{} ({})", status.name, query_addr);
+			println!("mode     : {}", status.mode);
+			println!("players  : {}/{}", status.players.len(), status.max_players);
+			for player in &status.players {
+				println!("  player {} ({:.0}ms)", player.id, player.ping);
+			}
+			println!("protocol : {}", status.protocol);
+			println!("uptime   : {:.0}s", status.uptime);
+		},
+		Err(err) => {
+			println!("Unable to query {}: {}", query_addr, err);
+			std::process::exit(1);
+		},
+	}
+}
+
+pub fn client(address : &str, class : Option<&str>, team : Option<&str>, chat : bool) {
+	let server_addr = server_address(address);
 
 	let event_loop = winit::event_loop::EventLoop::new();
-	let mut game_state = state::ClientGame::new(server_addr, None, None, &event_loop);
+	let mut game_state = state::ClientGame::new(server_addr, class, team, chat, None, None, &event_loop);
 
 	event_loop.run(move |event, _, control_flow| {
 
@@ -54,7 +93,11 @@ pub fn client(address : &str) {
 
 			Event::MainEventsCleared => {
 				game_state.run();
-				game_state.win_state.window.request_redraw();
+				if game_state.connected {
+					game_state.win_state.window.request_redraw();
+				} else {
+					*control_flow = ControlFlow::Exit;
+				}
 			},
 
 			Event::RedrawRequested(id) if id == game_state.win_state.id() => {
@@ -0,0 +1,106 @@
+use fnv::FnvHashMap;
+use serde_derive::{Serialize, Deserialize};
+
+use crate::reng::types::GLvec4;
+
+//What an entity is doing, as far as its sprite is concerned.
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Animation {
+	Idle,
+	Thrusting,
+	Damaged,
+	Exploding,
+}
+
+//How a sheet is cut into frames, in pixels of its own image.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Slicing {
+	//Equal frames, left to right and then top to bottom.
+	Grid { frame_size : (u32, u32) },
+	//Frames of any size, each as its upper left and lower right corners.
+	Frames(Vec<((u32, u32), (u32, u32))>),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Clip {
+	pub frames     : Vec<usize>,
+	//Seconds each frame is shown for.
+	pub frame_time : f32,
+	//Clips that don't loop hold their last frame.
+	#[serde(default = "looping")]
+	pub looping    : bool,
+}
+
+fn looping() -> bool {
+	true
+}
+
+//The metadata file sitting next to a sheet's image, like `assets/ShipSheet.json` for `assets/ShipSheet.png`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SheetMeta {
+	pub slicing    : Slicing,
+	pub animations : FnvHashMap<Animation, Clip>,
+}
+
+//A texture cut into frames, with its frames already placed in the packed spritesheet.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+	frames     : Vec<GLvec4>,
+	animations : FnvHashMap<Animation, Clip>,
+}
+
+impl SpriteSheet {
+	//`origin` is where the image's upper left corner ended up in a packed spritesheet of `sheet_dims` pixels.
+	pub fn new(meta : SheetMeta, image_dims : (u32, u32), origin : (u32, u32), sheet_dims : (u32, u32)) -> Result<Self, String> {
+		let rects = match meta.slicing {
+			Slicing::Grid { frame_size : (w, h) } if w == 0 || h == 0 => return Err(String::from("grid frames need a positive size")),
+			Slicing::Grid { frame_size : (w, h) } => (0..image_dims.1 / h)
+				.flat_map(|row| (0..image_dims.0 / w).map(move |column| ((column * w, row * h), ((column + 1) * w, (row + 1) * h))))
+				.collect::<Vec<_>>(),
+			Slicing::Frames(rects) => rects,
+		};
+		if rects.is_empty() {
+			return Err(String::from("the sheet has no frames"));
+		}
+		if let Some(rect) = rects.iter().find(|(ul, lr)| lr.0 > image_dims.0 || lr.1 > image_dims.1 || ul.0 >= lr.0 || ul.1 >= lr.1) {
+			return Err(format!("frame {:?} doesn't fit in a {}x{} image", rect, image_dims.0, image_dims.1));
+		}
+		for (animation, clip) in &meta.animations {
+			if clip.frames.is_empty() || clip.frame_time <= 0.0 {
+				return Err(format!("animation {:?} needs frames and a positive frame time", animation));
+			}
+			if let Some(frame) = clip.frames.iter().find(|frame| **frame >= rects.len()) {
+				return Err(format!("animation {:?} uses frame {}, but the sheet only has {}", animation, frame, rects.len()));
+			}
+		}
+
+		let to_text_coord = |(x, y) : (u32, u32)| ((origin.0 + x) as f32 / sheet_dims.0 as f32, (origin.1 + y) as f32 / sheet_dims.1 as f32);
+		let frames = rects.into_iter().map(|(ul, lr)| {
+			let (coord_ul, coord_lr) = (to_text_coord(ul), to_text_coord(lr));
+			GLvec4(coord_ul.0, coord_ul.1, coord_lr.0, coord_lr.1)
+		}).collect();
+
+		Ok(Self {
+			frames,
+			animations : meta.animations,
+		})
+	}
+
+	//Texture coordinates of the frame shown `time` seconds into an animation.
+	//Animations the sheet doesn't have fall back to idle, and to its first frame without that either.
+	pub fn frame(&self, animation : Animation, time : f32) -> GLvec4 {
+		let clip = match self.animations.get(&animation).or_else(|| self.animations.get(&Animation::Idle)) {
+			Some(clip) => clip,
+			None => return self.frames[0],
+		};
+		let step = (time.max(0.0) / clip.frame_time) as usize;
+		let index = if clip.looping {
+			step % clip.frames.len()
+		} else {
+			step.min(clip.frames.len() - 1)
+		};
+		self.frames[clip.frames[index]]
+	}
+}
@@ -1,28 +1,37 @@
 use super::types;
+use super::hud;
+use super::sheet::SpriteSheet;
 use crate::reng;
 use crate::reng::types::*;
 use crate::utils;
-use crate::world::World;
+use crate::world::{World, WorldEvent, Controls, EntityId};
 use crate::comms::*;
 
 use std::net;
+use std::sync::mpsc;
 use std::collections::VecDeque;
 use winit::event::VirtualKeyCode;
 use std::hash::Hash;
 use fnv::FnvHashMap;
 use strum::IntoEnumIterator;
 use strum_macros::{IntoStaticStr, EnumIter};
+use serde_derive::{Serialize, Deserialize};
 
-#[derive(IntoStaticStr, EnumIter, Hash, PartialEq, Eq, Clone, Copy)]
+//Sprites in the world name their texture, so this is sent along with it.
+#[derive(IntoStaticStr, EnumIter, Serialize, Deserialize, Hash, PartialEq, Eq, Clone, Copy, Debug)]
 pub enum ClientTexture {
 	Flat,
 	Player,
 	Ship,
+	ShipSheet,
+	#[strum(serialize = "sheild")]
+	Shield,
 }
 
 impl ClientTexture {
-	fn load_textures() -> (image::RgbaImage, FnvHashMap<ClientTexture, reng::types::GLvec4>) {
+	fn load_textures() -> (image::RgbaImage, FnvHashMap<ClientTexture, reng::types::GLvec4>, FnvHashMap<ClientTexture, SpriteSheet>) {
 		let mut map = FnvHashMap::default();
+		let mut sheets = FnvHashMap::default();
 
 		let mut rbga_images = Self::iter().map(|text| {
 			let file_name = format!("assets/{}.png", <&'static str>::from(text));
@@ -60,13 +69,26 @@ impl ClientTexture {
 			);
 
 			map.insert(*text, coords);
+
+			//Textures with a metadata file next to them are cut into animation frames.
+			let meta_path = format!("assets/{}.json", <&'static str>::from(*text));
+			if let Ok(json) = std::fs::read_to_string(&meta_path) {
+				let dims = ((pos.1).0 - (pos.0).0, (pos.1).1 - (pos.0).1);
+				let sheet = serde_json::from_str(&json).map_err(|err| err.to_string())
+					.and_then(|meta| SpriteSheet::new(meta, dims, pos.0, image_dims))
+					.unwrap_or_else(|err| panic!("unable to load {}: {}", meta_path, err));
+				sheets.insert(*text, sheet);
+			}
 		}
 
-		(spritesheet.0, map)
+		(spritesheet.0, map, sheets)
 
 	}
 }
 
+//Half of the world height visible on screen.
+const CAMERA_ZOOM : f32 = 3.0;
+
 pub struct ClientGame {
 	pub renderer       : reng::Renderer2D<types::Uniform, types::Instance2D>,
 	pub win_state      : types::WinState,
@@ -75,25 +97,38 @@ pub struct ClientGame {
 	pub instance_queue : Vec<types::Instance2D>,
 	pub action_queue   : VecDeque<TimestampedAction>,
 	pub texture_map    : FnvHashMap<ClientTexture, GLvec4>,
+	pub sheets         : FnvHashMap<ClientTexture, SpriteSheet>,
 	pub world          : World,
 	pub server         : TypedStream<TimestampedAction, TimestampedPerception>,
 	pub last_received  : f64,
 	pub last_processed : f64,
-	pub id             : usize,
+	//The pilot this client plays as.
+	pub id             : EntityId,
+	pub connected      : bool,
+	pub title          : String,
+	//Boarding and leaving happen once per press, not every frame the key is down.
+	pub board_held     : bool,
+	pub phase          : MatchPhase,
+	//Seconds left in the current phase, as of the last snapshot.
+	pub remaining      : Option<f32>,
+	pub ready          : bool,
+	pub ready_held     : bool,
+	//Lines typed into the terminal, sent as chat. Those starting with /t only go to the team.
+	pub chat           : mpsc::Receiver<String>,
 }
 
 impl ClientGame {
-	pub fn new(address : net::SocketAddr, vs_path : Option<&std::path::Path>, fs_path : Option<&std::path::Path>, event_loop: &winit::event_loop::EventLoopWindowTarget<()>,) -> Self {
+	pub fn new(address : net::SocketAddr, class : Option<&str>, team : Option<&str>, chat : bool, vs_path : Option<&std::path::Path>, fs_path : Option<&std::path::Path>, event_loop: &winit::event_loop::EventLoopWindowTarget<()>,) -> Self {
 
 		let win_state = types::WinState::new(event_loop);
 		let mut renderer  = reng::Renderer2D::<types::Uniform, types::Instance2D>::new(&win_state.window, 2, vs_path, fs_path);
 
 		let aspect = win_state.size.width as f32 / win_state.size.height as f32;
 		let uniform = types::Uniform {
-			ortho : cgmath::ortho(-aspect, aspect, -1., 1., -100., 100.),
+			ortho : camera_ortho((0.0, 0.0), aspect),
 		};
 
-		let (spritesheet, texture_map) = ClientTexture::load_textures();
+		let (spritesheet, texture_map, sheets) = ClientTexture::load_textures();
 
 		let text = renderer.create_texture_from_image(&spritesheet);
 		renderer.set_texture(&text);
@@ -108,8 +143,8 @@ impl ClientGame {
 		if let TimestampedPerception {
 			perception : Perception::ID(player_id),
 			..
-		} = bincode::deserialize_from(&stream).expect("Unable to get data from server") {
-			id = player_id as usize;
+		} = recv_blocking(&stream).unwrap_or_else(|err| panic!("Unable to get data from server: {:?}", err)) {
+			id = player_id;
 		} else {
 			panic!("Unable to get ID from server.")
 		}
@@ -119,7 +154,7 @@ impl ClientGame {
 		if let TimestampedPerception {
 			perception : Perception::World(init_world),
 			timestamp,
-		} = bincode::deserialize_from(&stream).expect("Unable to get data from server") {
+		} = recv_blocking(&stream).unwrap_or_else(|err| panic!("Unable to get data from server: {:?}", err)) {
 			world = init_world;
 			last_received = timestamp;
 		} else {
@@ -127,12 +162,13 @@ impl ClientGame {
 		}
 
 		let timestep  = utils::Timer::new();
-		ClientGame {
+		let mut game = ClientGame {
 			win_state,
 			renderer,
 			timestep,
 			uniform,
 			texture_map,
+			sheets,
 			instance_queue,
 			action_queue,
 			server : TypedStream::new(stream),
@@ -140,12 +176,81 @@ impl ClientGame {
 			id,
 			last_received,
 			last_processed : last_received,
+			connected : true,
+			title : String::new(),
+			board_held : false,
+			phase : MatchPhase::Lobby,
+			remaining : None,
+			ready : false,
+			ready_held : false,
+			//A server in the same process already reads stdin as its admin console, so it's left to that.
+			chat : if chat { spawn_chat() } else { mpsc::channel().1 },
+		};
+
+		//Choosing a class isn't predicted, so it skips the action queue. The server swaps the ship over and replicates it.
+		if let Some(class) = class {
+			game.send_action(&TimestampedAction {
+				timestamp : game.last_received,
+				action : Action::ChooseClass(String::from(class)),
+			});
+		}
+		if let Some(team) = team {
+			game.send_action(&TimestampedAction {
+				timestamp : game.last_received,
+				action : Action::ChooseTeam(String::from(team)),
+			});
 		}
+		game
 	}
 
 	pub fn draw(&mut self) {
 
-		self.world.render_to(&mut self.instance_queue, &self.texture_map);
+		let camera = self.world.pos(self.world.controlled(self.id)).unwrap_or((0.0, 0.0));
+		self.uniform.ortho = camera_ortho(camera, self.win_state.aspect);
+
+		self.world.render_to(&mut self.instance_queue, &self.texture_map, &self.sheets);
+		let hud_start = self.instance_queue.len();
+
+		let flat_text = self.texture_map[&ClientTexture::Flat];
+		let world = &self.world;
+		let mut title = match world.vehicle(self.id).and_then(|id| Some((id, world.ships.get(id)?))) {
+			Some((id, ship)) if !self.world.alive(id) => {
+				hud::death_screen(&mut self.instance_queue, flat_text, self.win_state.aspect, ship, &self.world.ship_params);
+				format!("surv - destroyed, respawning in {:.0}s", ship.respawn_timer.ceil())
+			},
+			Some((_, ship)) => {
+				hud::inventory(&mut self.instance_queue, flat_text, self.win_state.aspect, ship, &self.world.ship_params);
+				String::from("surv")
+			},
+			None => match self.world.healths.get(self.id) {
+				Some(health) => format!("surv - on foot, {:.0} health", health.hull.ceil()),
+				None => String::from("surv"),
+			},
+		};
+		title += &match (self.phase, self.remaining) {
+			(MatchPhase::Lobby, _) if self.ready => String::from(" - ready, waiting for the others"),
+			(MatchPhase::Lobby, _) => String::from(" - lobby, press R when ready"),
+			(MatchPhase::Countdown, Some(remaining)) => format!(" - starting in {:.0}s", remaining.ceil()),
+			(MatchPhase::Playing, Some(remaining)) => format!(" - {}:{:02} left", remaining as u32 / 60, remaining as u32 % 60),
+			(MatchPhase::RoundOver, _) => String::from(" - round over"),
+			_ => String::new(),
+		};
+		if let Some(team) = self.world.pilots.get(self.id).and_then(|pilot| pilot.team).and_then(|team| self.world.teams.get(team)) {
+			title += &format!(" - team {}", team.name);
+		}
+		if let Some(zone) = &self.world.zone {
+			match zone.countdown() {
+				Some(remaining) => title += &format!(" - zone shrinks in {:.0}s", remaining.ceil()),
+				None if zone.next().is_some() => title += " - zone shrinking",
+				None => {},
+			}
+		}
+		hud::to_world(&mut self.instance_queue[hud_start..], camera, CAMERA_ZOOM);
+
+		if title != self.title {
+			self.win_state.window.set_title(&title);
+			self.title = title;
+		}
 
 		let instances = self.instance_queue.as_slice();
 		self.renderer.draw_test(&self.uniform, instances);
@@ -155,12 +260,28 @@ impl ClientGame {
 	pub fn resize(&mut self, dims : winit::dpi::PhysicalSize<u32>) {
 		self.renderer.resize(dims);
 		self.win_state.resize(dims);
-		self.uniform.ortho = cgmath::ortho(-self.win_state.aspect, self.win_state.aspect, -1., 1., -1., 1.);
+		let camera = self.world.pos(self.world.controlled(self.id)).unwrap_or((0.0, 0.0));
+		self.uniform.ortho = camera_ortho(camera, self.win_state.aspect);
 	}
 
 	pub fn run(&mut self) {
+		if !self.connected {
+			return;
+		}
+
 		self.generate_actions();
 
+		while let Ok(line) = self.chat.try_recv() {
+			let action = match line.strip_prefix("/t ") {
+				Some(text) => Action::TeamMessage(text.to_owned()),
+				None => Action::Message(line),
+			};
+			self.send_action(&TimestampedAction {
+				timestamp : self.last_received,
+				action,
+			});
+		}
+
 		for action in &self.action_queue {
 			if self.last_processed < action.timestamp {
 				self.world.process(self.id, &action.action);
@@ -168,7 +289,12 @@ impl ClientGame {
 			}
 		}
 
-		self.world.update(self.timestep.secs());
+		//The server holds the world still through the countdown and the scoreboard, so prediction does too.
+		if self.phase.live() {
+			self.world.update(self.timestep.secs());
+		}
+		//Predicted events aren't used for anything yet, the server's own ones arrive with its snapshots.
+		self.world.events.drain();
 
 		loop {
 			match self.server.recv() {
@@ -188,6 +314,59 @@ impl ClientGame {
 							}
 
 						},
+						Message(message) => println!("Server: {}", message),
+						Ping(sent) => {
+							let ts_act = TimestampedAction {
+								timestamp : std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64(),
+								action : Action::Pong(sent),
+							};
+							self.send_action(&ts_act);
+						},
+						Events(events) => {
+							for event in events {
+								match event.event {
+									WorldEvent::Respawned { ship } => println!("Ship {} respawned", ship),
+									WorldEvent::Boarded { pilot, ship } if pilot == self.id => println!("Boarded ship {}", ship),
+									WorldEvent::Left { pilot, ship } if pilot == self.id => println!("Left ship {}", ship),
+									WorldEvent::Captured { team } => if let Some(team) = self.world.teams.get(team) {
+										println!("Team {} took the capture point", team.name);
+									},
+									_ => {},
+								}
+							}
+						},
+						Kill(kill) => match kill.killer {
+							Some(killer) => println!("{} destroyed {}", killer, kill.victim),
+							None => println!("{} was destroyed", kill.victim),
+						},
+						Scoreboard(standings, teams) => {
+							println!("Final standings");
+							for team in teams {
+								println!("team {:<7}{:>6.0}", team.name, team.score);
+							}
+							println!("{:<12}{:<8}{:>6}{:>8}{:>8}{:>10}", "player", "team", "kills", "deaths", "damage", "survived");
+							for player in standings {
+								let team = player.team.as_deref().unwrap_or("-");
+								println!("{:<12}{:<8}{:>6}{:>8}{:>8.0}{:>9.0}s", player.name, team, player.kills, player.deaths, player.damage, player.survival);
+							}
+						},
+						Phase(phase, remaining) => {
+							if phase != self.phase {
+								println!("{}", match phase {
+									MatchPhase::Lobby => "Back in the lobby, press R when ready",
+									MatchPhase::Countdown => "Round starting",
+									MatchPhase::Playing => "Go!",
+									MatchPhase::RoundOver => "Round over",
+								});
+								self.ready = false;
+							}
+							self.phase = phase;
+							self.remaining = remaining;
+						},
+						Shutdown(reason) => {
+							self.disconnect(&format!("Server shut down: {}", reason));
+							break;
+						},
 						_ => (),
 					}
 				},
@@ -196,7 +375,8 @@ impl ClientGame {
 				Err(bincode::ErrorKind::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
 
 				Err(err) => {
-					panic!("{:?}", err);
+					self.disconnect(&format!("Lost connection to server: {:?}", err));
+					break;
 				},
 			}
 		}
@@ -205,17 +385,104 @@ impl ClientGame {
 	}
 
 	fn generate_actions(&mut self) {
-		let player_ship = self.world.ships.get(self.id).unwrap();
-		let turn_dir = *self.win_state.keymap.get(&VirtualKeyCode::A).unwrap_or(&false) as i8 - *self.win_state.keymap.get(&VirtualKeyCode::D).unwrap_or(&false) as i8;
-		if turn_dir != player_ship.turning {
-			let action = Action::TurnShip(turn_dir);
-			let timestamp = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64();
+		let key = |key| *self.win_state.keymap.get(&key).unwrap_or(&false);
+		let controls = Controls {
+			turning : key(VirtualKeyCode::A) as i8 - key(VirtualKeyCode::D) as i8,
+			strafing : key(VirtualKeyCode::E) as i8 - key(VirtualKeyCode::Q) as i8,
+			thrusting : key(VirtualKeyCode::W),
+			braking : key(VirtualKeyCode::S),
+			firing : key(VirtualKeyCode::Space),
+			shielding : key(VirtualKeyCode::F),
+		};
+		//On foot the same keys walk along the world axes instead.
+		let walking = (key(VirtualKeyCode::D) as i8 - key(VirtualKeyCode::A) as i8, key(VirtualKeyCode::W) as i8 - key(VirtualKeyCode::S) as i8);
+		let board = key(VirtualKeyCode::X);
+
+		let vehicle = self.world.vehicle(self.id);
+		let ready = key(VirtualKeyCode::R);
+		if ready && !self.ready_held && self.phase == MatchPhase::Lobby {
+			self.ready = !self.ready;
+			//Like choosing a class, readying up isn't predicted and skips the action queue.
 			let ts_act = TimestampedAction {
-				timestamp,
-				action,
+				timestamp : self.last_received,
+				action : Action::Ready(self.ready),
 			};
-			self.action_queue.push_back(ts_act.clone());
-			self.server.send(&ts_act);
+			self.send_action(&ts_act);
+		}
+		self.ready_held = ready;
+
+		if board && !self.board_held {
+			self.queue_action(if vehicle.is_some() { Action::Leave } else { Action::Board });
+		}
+		self.board_held = board;
+
+		match vehicle {
+			Some(ship) => {
+				let player_ship = match self.world.ships.get(ship) {
+					Some(ship) => ship,
+					None => return,
+				};
+				for action in controls.actions_for(player_ship) {
+					self.queue_action(action);
+				}
+			},
+			None => {
+				if self.world.pilots.get(self.id).map_or(false, |pilot| pilot.walking != walking) {
+					self.queue_action(Action::Walk(walking.0, walking.1));
+				}
+			},
 		}
 	}
-}
\ No newline at end of file
+
+	fn queue_action(&mut self, action : Action) {
+		let mut timestamp = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64();
+		//Actions generated in the same frame must still be strictly ordered for prediction to replay them.
+		if let Some(last) = self.action_queue.back() {
+			timestamp = timestamp.max(last.timestamp + 1e-6);
+		}
+		let ts_act = TimestampedAction {
+			timestamp,
+			action,
+		};
+		self.action_queue.push_back(ts_act.clone());
+		self.send_action(&ts_act);
+	}
+
+	fn send_action(&mut self, ts_act : &TimestampedAction) {
+		if let Err(err) = self.server.send(ts_act) {
+			self.disconnect(&format!("Lost connection to server: {:?}", err));
+		}
+	}
+
+	pub fn disconnect(&mut self, reason : &str) {
+		if self.connected {
+			println!("{}", reason);
+			self.connected = false;
+			self.server.shutdown();
+		}
+	}
+}
+
+//Chat is typed into the terminal the client was started from, since the window has no text input.
+fn spawn_chat() -> mpsc::Receiver<String> {
+	use std::io::BufRead;
+	let (sender, receiver) = mpsc::channel();
+	std::thread::spawn(move || {
+		//Stops once stdin is closed or unreadable, or the game has gone.
+		for line in std::io::stdin().lock().lines() {
+			let line = match line {
+				Ok(line) => line,
+				Err(_) => break,
+			};
+			if !line.trim().is_empty() && sender.send(line).is_err() {
+				break;
+			}
+		}
+	});
+	receiver
+}
+
+fn camera_ortho(camera : (f32, f32), aspect : f32) -> cgmath::Matrix4<f32> {
+	let (w, h) = (aspect * CAMERA_ZOOM, CAMERA_ZOOM);
+	cgmath::ortho(camera.0 - w, camera.0 + w, camera.1 - h, camera.1 + h, -100., 100.)
+}
@@ -77,7 +77,7 @@ impl WinState {
 		use winit::event::{KeyboardInput, VirtualKeyCode, ElementState};
 		let KeyboardInput { virtual_keycode : key, state, .. } = input;
 		match key {
-			Some(key) if (VirtualKeyCode::A..VirtualKeyCode::Z).contains(&key) => {self.keymap.insert(key, state == ElementState::Pressed);},
+			Some(key) if (VirtualKeyCode::A..VirtualKeyCode::Z).contains(&key) || key == VirtualKeyCode::Space => {self.keymap.insert(key, state == ElementState::Pressed);},
 			_ => {},
 		}
 	}
@@ -2,12 +2,33 @@ use serde_derive::*;
 use std::net;
 use std::marker::PhantomData;
 use crate::world;
+use crate::format::FORMAT_VERSION;
+
+pub const QUERY_REQUEST : &[u8] = b"surv-query";
 
 #[derive(Serialize, Clone, Deserialize, Debug)]
 pub enum Action {
 	Disconnect,
 	Message(String),
 	TurnShip(i8),
+	Pong(f64),
+	Thrust(bool),
+	Brake(bool),
+	Strafe(i8),
+	Fire(bool),
+	RaiseShield(bool),
+	//Walking direction for a pilot on foot, in world axes.
+	Walk(i8, i8),
+	Board,
+	Leave,
+	//Only ever sent to the server, which checks the class exists before swapping the ship over.
+	ChooseClass(String),
+	//Ready checks only count in the lobby.
+	Ready(bool),
+	//Like classes, teams can only be picked in the lobby or while respawning.
+	ChooseTeam(String),
+	//Chat that only the sender's team sees.
+	TeamMessage(String),
 }
 
 #[derive(Serialize, Clone, Deserialize, Debug)]
@@ -18,8 +39,35 @@ pub struct TimestampedAction {
 
 #[derive(Serialize, Clone, Deserialize, Debug)]
 pub enum Perception {
-	ID(usize),
-	World(world::World)
+	//The pilot entity the client plays as.
+	ID(world::EntityId),
+	World(world::World),
+	Message(String),
+	Ping(f64),
+	Shutdown(String),
+	Events(Vec<world::TickEvent>),
+	Kill(Kill),
+	//Final standings, sent when a round ends. Teams are only ranked in team modes.
+	Scoreboard(Vec<PlayerStats>, Vec<TeamScore>),
+	//Sent with every snapshot, so late joiners and the countdown stay in step. Timed phases say how many seconds are left.
+	Phase(MatchPhase, Option<f32>),
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Debug)]
+pub enum MatchPhase {
+	//Warm-up while players join and ready up, nothing is scored.
+	Lobby,
+	//Everyone is in a fresh ship and the world stands still until the round starts.
+	Countdown,
+	Playing,
+	//The world stands still behind the scoreboard until the next lobby.
+	RoundOver,
+}
+
+impl MatchPhase {
+	pub fn live(self) -> bool {
+		self == MatchPhase::Lobby || self == MatchPhase::Playing
+	}
 }
 
 #[derive(Serialize, Clone, Deserialize, Debug)]
@@ -28,19 +76,71 @@ pub struct TimestampedPerception {
 	pub perception : Perception,
 }
 
+//One line of the kill feed, naming players rather than the ships they flew.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Kill {
+	//Nobody for ships lost to hazards, the zone or suffocation.
+	pub killer : Option<String>,
+	pub victim : String,
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct PlayerStats {
+	pub name     : String,
+	pub pilot    : world::EntityId,
+	pub bot      : bool,
+	pub team     : Option<String>,
+	pub kills    : u32,
+	pub deaths   : u32,
+	pub damage   : f32,
+	//Seconds spent alive this round, in a ship or on foot.
+	pub survival : f32,
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct TeamScore {
+	pub name  : String,
+	//Kills, or seconds holding the capture point.
+	pub score : f32,
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct PlayerStatus {
+	pub id   : usize,
+	pub ping : f32,
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct ServerStatus {
+	pub name        : String,
+	pub mode        : String,
+	pub players     : Vec<PlayerStatus>,
+	pub max_players : usize,
+	pub protocol    : u32,
+	pub uptime      : f32,
+}
+
 #[derive(Clone, Debug)]
 pub struct ClientComm {
 	pub stream : TypedStream<TimestampedPerception, TimestampedAction>,
 	pub timestamp : f64,
 	pub online : bool,
+	pub ping : f32,
+	//The pilot entity this client plays as.
+	pub pilot : world::EntityId,
+	//Cleared at the start of every lobby.
+	pub ready : bool,
 }
 
 impl ClientComm {
-	pub fn new(tcpstream : net::TcpStream) -> Self {
+	pub fn new(tcpstream : net::TcpStream, pilot : world::EntityId) -> Self {
 		ClientComm {
 			stream : TypedStream::new(tcpstream),
 			timestamp : std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64(),
 			online : true,
+			ping : 0.0,
+			pilot,
+			ready : false,
 		}
 	}
 
@@ -49,7 +149,7 @@ impl ClientComm {
 			timestamp : self.timestamp,
 			perception,
 		};
-		self.stream.send(&ts_perc);
+		let _ = self.stream.send(&ts_perc);
 	}
 
 	pub fn recv(&mut self) -> Result<TimestampedAction, bincode::ErrorKind>  {
@@ -64,6 +164,26 @@ impl ClientComm {
 
 }
 
+//Every message is prefixed with the sender's format version, so mismatched peers are reported rather than misread.
+//Unlike saves there are no wire migrations, a peer on another version is turned away.
+fn check_version(version : u32) -> Result<(), bincode::ErrorKind> {
+	if version == FORMAT_VERSION {
+		Ok(())
+	} else {
+		Err(bincode::ErrorKind::Custom(format!("peer uses format version {}, but this build uses version {}", version, FORMAT_VERSION)))
+	}
+}
+
+fn decode_versioned<R : serde::de::DeserializeOwned>(bytes : &[u8]) -> Result<R, bincode::ErrorKind> {
+	check_version(bincode::deserialize(bytes).map_err(|err| *err)?)?;
+	bincode::deserialize(&bytes[4..]).map_err(|err| *err)
+}
+
+pub fn recv_blocking<R : serde::de::DeserializeOwned>(stream : &net::TcpStream) -> Result<R, bincode::ErrorKind> {
+	check_version(bincode::deserialize_from(stream).map_err(|err| *err)?)?;
+	bincode::deserialize_from(stream).map_err(|err| *err)
+}
+
 #[derive(Debug)]
 pub struct TypedStream<S : serde::de::DeserializeOwned + serde::Serialize, R : serde::de::DeserializeOwned + serde::Serialize> {
 	pub stream  : net::TcpStream,
@@ -81,24 +201,34 @@ impl<S : serde::de::DeserializeOwned + serde::Serialize, R : serde::de::Deserial
 		}
 	}
 
-	pub fn send(&self, send : &S) {
-		bincode::serialize_into(&self.stream, send).unwrap();
+	pub fn send(&self, send : &S) -> Result<(), bincode::ErrorKind> {
+		bincode::serialize_into(&self.stream, &(FORMAT_VERSION, send)).map_err(|err| *err)
 	}
 
 	pub fn recv(&mut self) -> Result<R, bincode::ErrorKind> {
 		use std::io::Read;
 		let mut buff = [10u8; 256];
+		let mut closed = false;
 		loop {
 			match self.stream.read(&mut buff) {
+				Ok(0) => {
+					closed = true;
+					break;
+				},
 				Ok(n) => {
 					self.recv_buffer.extend(&buff[..n]);
 					if n < 256 { break }
 				},
+				Err(err) if err.kind() == std::io::ErrorKind::WouldBlock && !self.recv_buffer.is_empty() => break,
 				Err(err) => return Err(bincode::ErrorKind::Io(err)),
 			}
 		}
-		let recv = bincode::deserialize(&self.recv_buffer).map_err(|err| *err)?;
-		let size = bincode::serialized_size(&recv).map_err(|err| *err)?;
+		let recv = match decode_versioned(&self.recv_buffer) {
+			Ok(recv) => recv,
+			Err(bincode::ErrorKind::Io(_)) if closed => return Err(bincode::ErrorKind::Io(std::io::ErrorKind::ConnectionAborted.into())),
+			Err(err) => return Err(err),
+		};
+		let size = bincode::serialized_size(&(FORMAT_VERSION, &recv)).map_err(|err| *err)?;
 		self.recv_buffer.drain(..size as usize);
 		Ok(recv)
 	}
@@ -0,0 +1,514 @@
+use serde_json::Value;
+
+use crate::world::World;
+
+//Version 0 saves were headerless bincode and version 1 saves were bincode behind the header.
+//From version 2 onward the payload is json, so older saves can be upgraded field by field.
+//Only saves are migrated. Network messages share the version number but a mismatched peer is simply refused,
+//since client and server are expected to run the same build.
+pub const FORMAT_VERSION : u32 = 19;
+pub const SAVE_MAGIC     : &[u8; 8] = b"SURVSAVE";
+
+//Each entry upgrades a save payload from the given version to the one after it.
+const MIGRATIONS : &[(u32, fn(&mut Value))] = &[
+	(2, add_ship_controls),
+	(3, add_weapons),
+	(4, add_shields),
+	(5, add_respawning),
+	(6, add_arena),
+	(7, add_asteroids),
+	(8, add_inventories),
+	(9, add_zone),
+	(10, into_entities),
+	(11, add_ship_classes),
+	(12, add_rigid_bodies),
+	(13, add_world_rng),
+	(14, add_tick),
+	(15, add_pilots),
+	(16, add_teams),
+	(17, reseed_asteroids),
+	(18, add_pilot_health),
+];
+
+fn add_ship_controls(world : &mut Value) {
+	world["ship_params"] = serde_json::json!({
+		"max_speed" : 1.5,
+		"thrust" : 2.0,
+		"strafe_thrust" : 1.2,
+		"brake" : 3.0,
+		"drag" : 0.5,
+		"angular_damping" : 12.0,
+	});
+	for ship in world["ships"].as_array_mut().into_iter().flatten() {
+		ship["spin"] = Value::from(0.0);
+		ship["thrusting"] = Value::from(false);
+		ship["braking"] = Value::from(false);
+		ship["strafing"] = Value::from(0);
+	}
+}
+
+fn add_weapons(world : &mut Value) {
+	world["projectiles"] = serde_json::json!([]);
+	let params = &mut world["ship_params"];
+	params["max_hull"] = Value::from(100.0);
+	params["fire_cooldown"] = Value::from(0.25);
+	params["projectile_speed"] = Value::from(3.0);
+	params["projectile_life"] = Value::from(1.0);
+	params["projectile_damage"] = Value::from(10.0);
+	for ship in world["ships"].as_array_mut().into_iter().flatten() {
+		ship["firing"] = Value::from(false);
+		ship["cooldown"] = Value::from(0.0);
+		ship["hull"] = Value::from(100.0);
+	}
+}
+
+fn add_shields(world : &mut Value) {
+	let params = &mut world["ship_params"];
+	params["max_shield"] = Value::from(50.0);
+	params["shield_regen"] = Value::from(8.0);
+	params["shield_cooldown"] = Value::from(3.0);
+	params["max_energy"] = Value::from(100.0);
+	params["energy_regen"] = Value::from(15.0);
+	params["shield_drain"] = Value::from(20.0);
+	for ship in world["ships"].as_array_mut().into_iter().flatten() {
+		ship["shielding"] = Value::from(false);
+		ship["shield"] = Value::from(50.0);
+		ship["shield_cooldown"] = Value::from(0.0);
+		ship["energy"] = Value::from(100.0);
+	}
+}
+
+fn add_respawning(world : &mut Value) {
+	let params = &mut world["ship_params"];
+	params["respawn_delay"] = Value::from(5.0);
+	params["spawn_protection"] = Value::from(2.0);
+	for ship in world["ships"].as_array_mut().into_iter().flatten() {
+		ship["respawn_timer"] = Value::from(0.0);
+		ship["protection"] = Value::from(0.0);
+	}
+}
+
+fn add_arena(world : &mut Value) {
+	world["arena"] = serde_json::json!({
+		"half_size" : [8.0, 8.0],
+		"edge" : "Walls",
+	});
+}
+
+//Older worlds had nothing but ships, so they get an empty field rather than a freshly seeded one.
+fn add_asteroids(world : &mut Value) {
+	world["asteroids"] = serde_json::json!({
+		"seed" : 0,
+		"extent" : [0.0, 0.0],
+		"time" : 0.0,
+		"damaged" : {},
+		"spawned" : [],
+	});
+	world["pickups"] = serde_json::json!([]);
+}
+
+fn add_inventories(world : &mut Value) {
+	let params = &mut world["ship_params"];
+	params["fuel_capacity"] = Value::from(100.0);
+	params["ammo_capacity"] = Value::from(60.0);
+	params["scrap_capacity"] = Value::from(50.0);
+	params["oxygen_capacity"] = Value::from(100.0);
+	params["fuel_use"] = Value::from(5.0);
+	params["oxygen_use"] = Value::from(1.0);
+	params["suffocation"] = Value::from(10.0);
+	for ship in world["ships"].as_array_mut().into_iter().flatten() {
+		ship["inventory"] = serde_json::json!({
+			"fuel" : 100.0,
+			"ammo" : 60.0,
+			"scrap" : 0.0,
+			"oxygen" : 100.0,
+		});
+	}
+	//Pickups so far were all scrap from asteroids.
+	for pickup in world["pickups"].as_array_mut().into_iter().flatten() {
+		pickup["kind"] = Value::from("Scrap");
+	}
+}
+
+fn add_zone(world : &mut Value) {
+	world["zone"] = Value::Null;
+}
+
+//Ships, projectiles and pickups become entities numbered in that order, split into their components.
+fn into_entities(world : &mut Value) {
+	let take = |world : &mut Value, key : &str| match world[key].take() {
+		Value::Array(items) => items,
+		_ => vec![],
+	};
+	let ships = take(world, "ships");
+	let projectiles = take(world, "projectiles");
+	let pickups = take(world, "pickups");
+
+	let count = ships.len() + projectiles.len() + pickups.len();
+	let mut storages = ["transforms", "velocities", "ships", "healths", "owners", "sprites", "projectiles", "pickups"].iter()
+		.map(|name| (*name, vec![Value::Null; count]))
+		.collect::<std::collections::BTreeMap<_, _>>();
+	let mut insert = |storage : &str, index : usize, component : Value| {
+		storages.get_mut(storage).unwrap()[index] = serde_json::json!([0, component]);
+	};
+
+	for (index, mut ship) in ships.into_iter().enumerate() {
+		insert("transforms", index, serde_json::json!({ "pos" : ship["pos"].take(), "angle" : ship["angle"].take() }));
+		insert("velocities", index, serde_json::json!({ "vel" : ship["vel"].take(), "spin" : ship["spin"].take() }));
+		insert("healths", index, serde_json::json!({ "hull" : ship["hull"].take() }));
+		insert("sprites", index, serde_json::json!({ "texture" : "Ship", "tint" : [1.0, 1.0, 1.0, 1.0], "scale" : [0.5, 0.5], "rotation" : -90.0 }));
+		if let Some(ship) = ship.as_object_mut() {
+			for field in &["alive", "pos", "angle", "vel", "spin", "hull"] {
+				ship.remove(*field);
+			}
+		}
+		insert("ships", index, ship);
+	}
+
+	let offset = count - projectiles.len() - pickups.len();
+	for (n, mut projectile) in projectiles.into_iter().enumerate() {
+		let index = offset + n;
+		let vel = projectile["vel"].take();
+		let angle = vel[1].as_f64().unwrap_or(0.0).atan2(vel[0].as_f64().unwrap_or(0.0)).to_degrees();
+		insert("transforms", index, serde_json::json!({ "pos" : projectile["pos"].take(), "angle" : angle }));
+		insert("velocities", index, serde_json::json!({ "vel" : vel, "spin" : 0.0 }));
+		insert("owners", index, serde_json::json!({ "index" : projectile["owner"].take(), "generation" : 0 }));
+		insert("sprites", index, serde_json::json!({ "texture" : "Flat", "tint" : [1.0, 0.85, 0.3, 1.0], "scale" : [0.015, 0.05], "rotation" : -90.0 }));
+		insert("projectiles", index, serde_json::json!({ "life" : projectile["life"].take(), "damage" : projectile["damage"].take() }));
+	}
+
+	let offset = count - pickups.len();
+	for (n, mut pickup) in pickups.into_iter().enumerate() {
+		let index = offset + n;
+		let tint = match pickup["kind"].as_str() {
+			Some("Fuel") => [0.9, 0.5, 0.1, 1.0],
+			Some("Ammo") => [1.0, 0.85, 0.3, 1.0],
+			Some("Oxygen") => [0.3, 0.7, 1.0, 1.0],
+			_ => [0.6, 0.6, 0.6, 1.0],
+		};
+		insert("transforms", index, serde_json::json!({ "pos" : pickup["pos"].take(), "angle" : 0.0 }));
+		insert("sprites", index, serde_json::json!({ "texture" : "Flat", "tint" : tint, "scale" : [0.06, 0.06], "rotation" : 45.0 }));
+		insert("pickups", index, serde_json::json!({ "kind" : pickup["kind"].take(), "amount" : pickup["amount"].take() }));
+	}
+
+	world["entities"] = serde_json::json!({
+		"generations" : vec![0; count],
+		"alive" : vec![true; count],
+		"free" : [],
+	});
+	for (name, slots) in storages {
+		world[name] = serde_json::json!({ "slots" : slots });
+	}
+}
+
+//Thrust, hull and shield move from the shared parameters into a class every existing ship now flies.
+fn add_ship_classes(world : &mut Value) {
+	let params = &mut world["ship_params"];
+	let fighter = serde_json::json!({
+		"name" : "fighter",
+		"sprite" : "Ship",
+		"tint" : [1.0, 1.0, 1.0, 1.0],
+		"size" : 0.4,
+		"turn_rate" : 250.0,
+		"thrust" : params["thrust"].take(),
+		"mass" : 1.0,
+		"hull" : params["max_hull"].take(),
+		"shield" : params["max_shield"].take(),
+		"weapon_slots" : [{ "offset" : [0.4, 0.0], "angle" : 0.0 }],
+	});
+	if let Some(params) = params.as_object_mut() {
+		for field in &["thrust", "max_hull", "max_shield"] {
+			params.remove(*field);
+		}
+	}
+	world["classes"] = serde_json::json!([fighter]);
+
+	if let Some(slots) = world["ships"]["slots"].as_array_mut() {
+		for ship in slots.iter_mut().filter_map(|slot| slot.get_mut(1)) {
+			ship["class"] = serde_json::json!("fighter");
+		}
+	}
+}
+
+//Ships get a rigid body sized from their class, their acceleration now lives in its force.
+fn add_rigid_bodies(world : &mut Value) {
+	world["ship_params"]["projectile_impulse"] = Value::from(0.3);
+	world["lag"] = Value::from(0.0);
+
+	let params = world["ship_params"].clone();
+	let classes = world["classes"].as_array().cloned().unwrap_or_default();
+	let mut slots = vec![];
+	for slot in world["ships"]["slots"].as_array_mut().into_iter().flatten() {
+		let (generation, ship) = match slot.as_array_mut() {
+			Some(pair) if pair.len() == 2 => (pair[0].clone(), &mut pair[1]),
+			_ => {
+				slots.push(Value::Null);
+				continue;
+			},
+		};
+		if let Some(ship) = ship.as_object_mut() {
+			ship.remove("acc");
+		}
+		let class = classes.iter().find(|class| class["name"] == ship["class"]).or_else(|| classes.first());
+		let mass = class.and_then(|class| class["mass"].as_f64()).unwrap_or(1.0);
+		let size = class.and_then(|class| class["size"].as_f64()).unwrap_or(0.4);
+		let (x, y) = (size * 0.75, size);
+		slots.push(serde_json::json!([generation, {
+			"mass" : mass,
+			"inertia" : mass * (x * x + y * y) / 3.0,
+			"drag" : params["drag"],
+			"angular_drag" : params["angular_damping"],
+			"max_speed" : params["max_speed"],
+			"force" : [0.0, 0.0],
+			"torque" : 0.0,
+		}]));
+	}
+	world["bodies"] = serde_json::json!({ "slots" : slots });
+}
+
+//Any state is a valid starting point for the generator, so older saves start it from their asteroid seed.
+fn add_world_rng(world : &mut Value) {
+	world["ship_params"]["projectile_spread"] = Value::from(2.0);
+	let seed = world["asteroids"]["seed"].as_u64().unwrap_or(0);
+	world["rng"] = serde_json::json!({
+		"state" : seed,
+		"inc" : 1442695040888963407u64,
+	});
+}
+
+fn add_tick(world : &mut Value) {
+	world["tick"] = Value::from(0);
+}
+
+//Nobody was aboard the ships of older saves, the server frees them up on load anyway.
+fn add_pilots(world : &mut Value) {
+	world["ship_params"]["walk_speed"] = Value::from(0.6);
+	world["ship_params"]["board_range"] = Value::from(0.3);
+	world["ship_params"]["leave_speed"] = Value::from(0.1);
+	world["pilots"] = serde_json::json!({ "slots" : [] });
+
+	if let Some(slots) = world["ships"]["slots"].as_array_mut() {
+		for ship in slots.iter_mut().filter_map(|slot| slot.get_mut(1)) {
+			ship["pilot"] = Value::Null;
+		}
+	}
+}
+
+//Older saves are free for all, with nobody on a team and friendly fire off.
+fn add_teams(world : &mut Value) {
+	world["ship_params"]["friendly_fire"] = Value::from(0.0);
+	world["teams"] = serde_json::json!([]);
+	world["capture"] = Value::Null;
+
+	if let Some(slots) = world["pilots"]["slots"].as_array_mut() {
+		for pilot in slots.iter_mut().filter_map(|slot| slot.get_mut(1)) {
+			pilot["team"] = Value::Null;
+		}
+	}
+}
+
+//Fields are now generated with the world's own generator, so the same seed lays out different asteroids.
+//Damage recorded against the old layout would land on the wrong ones.
+fn reseed_asteroids(world : &mut Value) {
+	world["asteroids"]["damaged"] = serde_json::json!({});
+}
+
+//Pilots on foot get health of their own. Any saved on foot are cleared when the server loads the world anyway.
+fn add_pilot_health(world : &mut Value) {
+	world["ship_params"]["pilot_health"] = Value::from(20.0);
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+	Io(std::io::Error),
+	NotASave,
+	NewerVersion(u32),
+	Corrupt(String),
+}
+
+impl std::fmt::Display for FormatError {
+	fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+		use FormatError::*;
+		match self {
+			Io(err) => write!(f, "{}", err),
+			NotASave => write!(f, "not a surv save file"),
+			NewerVersion(version) => write!(f, "save file is version {}, but this build only understands up to version {}", version, FORMAT_VERSION),
+			Corrupt(err) => write!(f, "save file is corrupt ({})", err),
+		}
+	}
+}
+
+impl From<std::io::Error> for FormatError {
+	fn from(err : std::io::Error) -> Self {
+		FormatError::Io(err)
+	}
+}
+
+impl From<serde_json::Error> for FormatError {
+	fn from(err : serde_json::Error) -> Self {
+		FormatError::Corrupt(err.to_string())
+	}
+}
+
+pub fn write_world(world : &World, path : &std::path::Path) -> Result<(), FormatError> {
+	let mut bytes = SAVE_MAGIC.to_vec();
+	bytes.extend(&FORMAT_VERSION.to_le_bytes());
+	serde_json::to_writer(&mut bytes, world)?;
+	std::fs::write(path, bytes)?;
+	Ok(())
+}
+
+pub fn read_world(path : &std::path::Path) -> Result<World, FormatError> {
+	let bytes = std::fs::read(path)?;
+	let (version, payload) = if bytes.len() >= 12 && bytes.starts_with(SAVE_MAGIC) {
+		let mut version = [0u8; 4];
+		version.copy_from_slice(&bytes[8..12]);
+		(u32::from_le_bytes(version), &bytes[12..])
+	} else {
+		(0, &bytes[..])
+	};
+
+	let value = match version {
+		0 => {
+			let world = bincode::deserialize::<legacy::WorldV1>(payload).map_err(|_| FormatError::NotASave)?;
+			serde_json::to_value(world)?
+		},
+		1 => {
+			let world = bincode::deserialize::<legacy::WorldV1>(payload).map_err(|err| FormatError::Corrupt(err.to_string()))?;
+			serde_json::to_value(world)?
+		},
+		version if version > FORMAT_VERSION => return Err(FormatError::NewerVersion(version)),
+		_ => serde_json::from_slice(payload)?,
+	};
+
+	Ok(serde_json::from_value(migrate(version.max(2), value))?)
+}
+
+pub fn migrate(version : u32, mut value : Value) -> Value {
+	for (from, migration) in MIGRATIONS {
+		if *from >= version {
+			migration(&mut value);
+		}
+	}
+	value
+}
+
+//Frozen copies of types whose layout has since changed, kept so old saves can still be decoded.
+mod legacy {
+	use serde_derive::*;
+
+	#[derive(Serialize, Deserialize)]
+	pub struct WorldV1 {
+		pub ships : Vec<ShipV1>,
+	}
+
+	#[derive(Serialize, Deserialize)]
+	pub struct ShipV1 {
+		pub alive : bool,
+		pub angle : f32,
+		pub turning : i8,
+		pub pos : (f32, f32),
+		pub vel : (f32, f32),
+		pub acc : (f32, f32),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::{Edge, EntityId};
+
+	fn fixture(name : &str) -> std::path::PathBuf {
+		std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+	}
+
+	fn id(index : u32) -> EntityId {
+		EntityId { index, generation : 0 }
+	}
+
+	//The v0, v1 and v2 fixtures hold the same two ships, the second one dead, in each version's encoding.
+	fn check_two_ships(world : &World) {
+		assert_eq!(world.ships.ids().collect::<Vec<_>>(), vec![id(0), id(1)]);
+		let transform = world.transforms.get(id(0)).unwrap();
+		assert_eq!((transform.pos, transform.angle), ((1.0, 2.0), 90.0));
+		assert_eq!(world.velocities.get(id(0)).unwrap().vel, (0.5, 0.0));
+		assert_eq!(world.transforms.get(id(1)).unwrap().pos, (-3.0, 4.0));
+
+		let ship = world.ships.get(id(0)).unwrap();
+		assert_eq!((ship.class.as_str(), ship.turning, ship.pilot), ("fighter", 1, None));
+		assert_eq!(world.classes.iter().map(|class| class.name.as_str()).collect::<Vec<_>>(), vec!["fighter"]);
+		assert!(world.bodies.get(id(0)).is_some());
+		assert!(world.healths.get(id(1)).is_some());
+
+		assert_eq!(world.projectiles.ids().count() + world.pickups.ids().count() + world.pilots.ids().count(), 0);
+		assert!(world.zone.is_none() && world.capture.is_none() && world.teams.is_empty());
+		assert_eq!((world.tick, world.ship_params.friendly_fire), (0, 0.0));
+	}
+
+	#[test]
+	fn reads_headerless_bincode() {
+		check_two_ships(&read_world(&fixture("v0.save")).unwrap());
+	}
+
+	#[test]
+	fn reads_bincode_behind_header() {
+		check_two_ships(&read_world(&fixture("v1.save")).unwrap());
+	}
+
+	#[test]
+	fn reads_first_json_version() {
+		check_two_ships(&read_world(&fixture("v2.save")).unwrap());
+	}
+
+	//Saved by a version 10 build mid match, with projectiles in flight and pickups lying around.
+	#[test]
+	fn splits_version_10_into_entities() {
+		let world = read_world(&fixture("v10.save")).unwrap();
+		assert_eq!(world.ships.ids().collect::<Vec<_>>(), vec![id(0), id(1)]);
+		assert_eq!(world.projectiles.ids().collect::<Vec<_>>(), vec![id(2), id(3)]);
+		assert_eq!(world.pickups.ids().count(), 7);
+		assert!(world.pickups.ids().all(|pickup| pickup.index >= 4 && world.transforms.get(pickup).is_some()));
+		for projectile in world.projectiles.ids() {
+			assert!(world.ships.contains(world.owners.get(projectile).unwrap().0));
+		}
+		assert_eq!(world.ships.get(id(0)).unwrap().inventory.ammo, 58.0);
+		assert_eq!(world.asteroids.state.seed, 15186220079050550068);
+		assert_eq!(world.arena.edge, Edge::Walls);
+		assert!(world.bodies.get(id(1)).is_some() && world.pilots.ids().count() == 0);
+	}
+
+	#[test]
+	fn round_trips_current_version() {
+		let path = std::env::temp_dir().join(format!("surv-format-{}.save", std::process::id()));
+		let world = read_world(&fixture("v10.save")).unwrap();
+		write_world(&world, &path).unwrap();
+		let read = read_world(&path);
+		std::fs::remove_file(&path).unwrap();
+		let read = read.unwrap();
+		assert_eq!(read.ships.ids().count(), 2);
+		assert_eq!(read.transforms.get(id(1)).unwrap().pos, world.transforms.get(id(1)).unwrap().pos);
+	}
+
+	#[test]
+	fn rejects_newer_versions() {
+		let path = std::env::temp_dir().join(format!("surv-newer-{}.save", std::process::id()));
+		let mut bytes = SAVE_MAGIC.to_vec();
+		bytes.extend(&(FORMAT_VERSION + 1).to_le_bytes());
+		bytes.extend(b"{}");
+		std::fs::write(&path, bytes).unwrap();
+		let read = read_world(&path);
+		std::fs::remove_file(&path).unwrap();
+		match read {
+			Err(FormatError::NewerVersion(version)) => assert_eq!(version, FORMAT_VERSION + 1),
+			other => panic!("expected a newer version error, got {:?}", other.map(|_| ())),
+		}
+	}
+
+	#[test]
+	fn rejects_files_that_are_not_saves() {
+		match read_world(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")) {
+			Err(FormatError::NotASave) => {},
+			other => panic!("expected a not a save error, got {:?}", other.map(|_| ())),
+		}
+	}
+}
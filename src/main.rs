@@ -6,30 +6,37 @@ mod client;
 mod reng;
 mod world;
 mod comms;
+mod format;
 
 fn main() {
 	let args : Vec<String> = env::args().collect();
 	let address = args.get(2).map(|s| s.as_str()).unwrap_or("127.0.0.1:8778");
+	let class = args.iter().position(|arg| arg == "--class").and_then(|i| args.get(i + 1)).map(|s| s.as_str());
+	let team = args.iter().position(|arg| arg == "--team").and_then(|i| args.get(i + 1)).map(|s| s.as_str());
 	if args.len() >= 2 {
 		match args[1].as_str() {
 			"host" => {
-				server::server();
+				server::server(server::ServerConfig::from_args(&args[2..]));
 			},
 			"client" => {
-				client::client(address);
+				client::client(address, class, team, true);
 			},
 			"local" => {
+				let config = server::ServerConfig::from_args(&args[2..]);
 				std::thread::spawn(move || {
-					server::server();
+					server::server(config);
 				});
-				client::client(address);
+				client::client(address, class, team, false);
+			},
+			"query" => {
+				client::query(address, args.iter().any(|arg| arg == "--json"));
 			},
 			_ => {
 				println!("Invalid argument, exiting process.");
 			},
 		}
 	} else {
-		client::client(address);
+		client::client(address, None, None, true);
 	}
 
-}
\ No newline at end of file
+}
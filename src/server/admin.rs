@@ -0,0 +1,259 @@
+use serde_derive::*;
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+use std::thread;
+
+use super::bot::Difficulty;
+use crate::world::EntityId;
+
+#[cfg(unix)]
+pub const SOCKET_PATH : &str = "surv-admin/admin.sock";
+#[cfg(unix)]
+pub const TOKEN_VAR   : &str = "SURV_ADMIN_TOKEN";
+
+#[derive(Clone, Debug)]
+pub enum AdminCommand {
+	Help,
+	Players,
+	Kick(usize, String),
+	Ban(usize, String),
+	Broadcast(String),
+	Pause,
+	Resume,
+	SendRate(f32),
+	AddBot(Difficulty),
+	RemoveBot(Option<EntityId>),
+	Save(String),
+	Shutdown(String),
+}
+
+impl AdminCommand {
+	pub const USAGE : &'static str = "\
+players                 list connected players
+kick <id> [reason]      disconnect a player
+ban <id> [reason]       disconnect a player and refuse their address
+say <message>           broadcast a message to every player
+pause | resume          stop or restart the simulation
+sendrate <hz>           change how often the world is sent to clients
+bot add [difficulty]    add an ai pilot, easy, normal or hard
+bot remove [ship]       remove a bot, the most recent one by default
+save [file]             write the current world to disk
+shutdown [reason]       notify every player and stop the server
+prefix any command with 'json' for machine-readable output";
+
+	pub fn parse(line : &str) -> Result<Self, String> {
+		let mut words = line.split_whitespace();
+		let command = words.next().ok_or_else(|| String::from("empty command"))?;
+		let rest = |words : std::str::SplitWhitespace| words.collect::<Vec<_>>().join(" ");
+
+		let player_id = |word : Option<&str>| {
+			word.ok_or_else(|| format!("'{}' expects a player id", command))?
+				.parse::<usize>()
+				.map_err(|_| format!("'{}' expects a player id", command))
+		};
+
+		use AdminCommand::*;
+		Ok(match command {
+			"help" => Help,
+			"players" | "list" => Players,
+			"kick" => Kick(player_id(words.next())?, rest(words)),
+			"ban" => Ban(player_id(words.next())?, rest(words)),
+			"say" | "broadcast" => Broadcast(rest(words)),
+			"pause" => Pause,
+			"resume" => Resume,
+			"sendrate" => {
+				let rate = words.next()
+					.and_then(|word| word.parse::<f32>().ok())
+					.filter(|rate| *rate > 0.0)
+					.ok_or_else(|| String::from("'sendrate' expects a positive number"))?;
+				SendRate(rate)
+			},
+			"bot" => match words.next() {
+				Some("add") => AddBot(words.next().map_or(Ok(Difficulty::default()), str::parse)?),
+				//Ships are named by index and generation, like 3v0, so a stale id never picks out whoever flies that slot now.
+				Some("remove") => RemoveBot(words.next().map(str::parse).transpose().map_err(|err| format!("'bot remove' expects a ship id: {}", err))?),
+				_ => return Err(String::from("'bot' expects 'add' or 'remove'")),
+			},
+			"save" => Save(words.next().unwrap_or("world.sav").to_owned()),
+			"shutdown" | "quit" => Shutdown(rest(words)),
+			_ => return Err(format!("unknown command '{}', try 'help'", command)),
+		})
+	}
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AdminReply {
+	pub ok      : bool,
+	pub message : String,
+	pub data    : serde_json::Value,
+}
+
+impl AdminReply {
+	pub fn ok(message : String) -> Self {
+		Self {
+			ok : true,
+			message,
+			data : serde_json::Value::Null,
+		}
+	}
+
+	pub fn err(message : String) -> Self {
+		Self {
+			ok : false,
+			message,
+			data : serde_json::Value::Null,
+		}
+	}
+
+	pub fn with_data(mut self, data : serde_json::Value) -> Self {
+		self.data = data;
+		self
+	}
+
+	pub fn format(&self, json : bool) -> String {
+		if json {
+			serde_json::to_string(self).unwrap()
+		} else if self.ok {
+			self.message.clone()
+		} else {
+			format!("error: {}", self.message)
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct AdminRequest {
+	pub command : AdminCommand,
+	pub reply   : mpsc::Sender<AdminReply>,
+}
+
+//Parses and forwards a single line to the server, returning None once the server has stopped.
+fn execute(requests : &mpsc::Sender<AdminRequest>, line : &str) -> Option<String> {
+	let line = line.trim();
+	let (json, line) = match line.strip_prefix("json") {
+		Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => (true, rest.trim()),
+		_ => (false, line),
+	};
+
+	let reply = match AdminCommand::parse(line) {
+		Ok(command) => {
+			let (reply, response) = mpsc::channel();
+			requests.send(AdminRequest { command, reply }).ok()?;
+			response.recv().ok()?
+		},
+		Err(err) => AdminReply::err(err),
+	};
+
+	Some(reply.format(json))
+}
+
+pub fn spawn_stdin(requests : mpsc::Sender<AdminRequest>) {
+	thread::spawn(move || {
+		let stdin = io::stdin();
+		for line in stdin.lock().lines() {
+			let line = match line {
+				Ok(line) => line,
+				Err(_) => break,
+			};
+
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			match execute(&requests, &line) {
+				Some(output) => println!("{}", output),
+				None => break,
+			}
+		}
+	});
+}
+
+#[cfg(unix)]
+pub fn admin_token() -> String {
+	std::env::var(TOKEN_VAR).unwrap_or_else(|_| {
+		use rand::Rng;
+		let mut rng = rand::thread_rng();
+		(0..16).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+	})
+}
+
+#[cfg(unix)]
+pub fn spawn_socket(path : &'static str, token : String, requests : mpsc::Sender<AdminRequest>) {
+	use std::io::Write;
+	use std::os::unix::net::UnixListener;
+	use std::os::unix::fs::PermissionsExt;
+
+	//The socket is only reachable through a directory nobody else can enter, so there's no moment after binding
+	//where other local users could connect before its own permissions are tightened.
+	if let Some(dir) = std::path::Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		use std::os::unix::fs::DirBuilderExt;
+		let private = std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+			.and_then(|()| std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)));
+		if let Err(err) = private {
+			println!("Unable to open admin socket at {}: {}", path, err);
+			return;
+		}
+	}
+	let _ = std::fs::remove_file(path);
+	let listener = match UnixListener::bind(path) {
+		Ok(listener) => listener,
+		Err(err) => {
+			println!("Unable to open admin socket at {}: {}", path, err);
+			return;
+		},
+	};
+	let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+
+	println!("Admin socket listening on {}", path);
+
+	thread::spawn(move || {
+		for stream in listener.incoming() {
+			let stream = match stream {
+				Ok(stream) => stream,
+				Err(_) => continue,
+			};
+
+			let token = token.clone();
+			let requests = requests.clone();
+			thread::spawn(move || {
+				let mut writer = match stream.try_clone() {
+					Ok(writer) => writer,
+					Err(_) => return,
+				};
+				let mut lines = io::BufReader::new(stream).lines();
+
+				match lines.next() {
+					Some(Ok(line)) if same_token(line.trim(), &token) => {
+						let _ = writeln!(writer, "ok");
+					},
+					_ => {
+						let _ = writeln!(writer, "error: invalid token");
+						return;
+					},
+				}
+
+				for line in lines {
+					let line = match line {
+						Ok(line) => line,
+						Err(_) => break,
+					};
+
+					if line.trim().is_empty() {
+						continue;
+					}
+
+					match execute(&requests, &line) {
+						Some(output) => if writeln!(writer, "{}", output).is_err() { break },
+						None => break,
+					}
+				}
+			});
+		}
+	});
+}
+
+//Looks at every byte whatever the first difference, so response times don't give away how much of a guess was right.
+#[cfg(unix)]
+fn same_token(guess : &str, token : &str) -> bool {
+	guess.len() == token.len() && guess.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -0,0 +1,235 @@
+use rand::Rng;
+
+use crate::comms::Action;
+use crate::world::{self, World, WorldRng, Controls, Edge, EntityId};
+
+//Bots start steering back towards the middle this far from a solid or damaging edge.
+const EDGE_MARGIN : f32 = 1.5;
+//How far ahead of the ship asteroids are steered around.
+const AVOID_RANGE : f32 = 1.0;
+//Below this fraction of its hull a bot runs instead of fighting.
+const FLEE_HULL   : f32 = 0.3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Difficulty {
+	//Seconds between decisions.
+	pub reaction      : f32,
+	//How far off target, in degrees, a bot is willing to fire.
+	pub aim_tolerance : f32,
+	//How far away enemies are noticed.
+	pub sight         : f32,
+	//Whether bots aim where a target is going rather than where it is.
+	pub lead          : bool,
+}
+
+impl Default for Difficulty {
+	fn default() -> Self {
+		Self {
+			reaction : 0.25,
+			aim_tolerance : 10.0,
+			sight : 6.0,
+			lead : true,
+		}
+	}
+}
+
+impl std::str::FromStr for Difficulty {
+	type Err = String;
+
+	fn from_str(difficulty : &str) -> Result<Self, Self::Err> {
+		match difficulty {
+			"easy" => Ok(Self { reaction : 0.5, aim_tolerance : 20.0, sight : 4.0, lead : false }),
+			"normal" => Ok(Self::default()),
+			"hard" => Ok(Self { reaction : 0.1, aim_tolerance : 4.0, sight : 8.0, lead : true }),
+			_ => Err(format!("unknown difficulty '{}', expected easy, normal or hard", difficulty)),
+		}
+	}
+}
+
+//What a bot can see of a ship.
+#[derive(Clone, Copy, Debug)]
+struct Craft {
+	pos   : (f32, f32),
+	vel   : (f32, f32),
+	angle : f32,
+}
+
+impl Craft {
+	fn of(world : &World, id : EntityId) -> Option<Self> {
+		let transform = world.transforms.get(id)?;
+		let velocity = world.velocities.get(id)?;
+		Some(Self { pos : transform.pos, vel : velocity.vel, angle : transform.angle })
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct Bot {
+	pub pilot      : EntityId,
+	pub difficulty : Difficulty,
+	wander         : f32,
+	think_timer    : f32,
+	//Forked from the world's generator, so a replay with the same bots makes the same decisions.
+	rng            : WorldRng,
+}
+
+impl Bot {
+	pub fn new(pilot : EntityId, difficulty : Difficulty, rng : WorldRng) -> Self {
+		Self {
+			pilot,
+			difficulty,
+			wander : 0.0,
+			think_timer : 0.0,
+			rng,
+		}
+	}
+
+	//Bots fly by the same actions a client would send, so the world can't tell them apart from players.
+	pub fn think(&mut self, world : &World, timestep : f32) -> Vec<Action> {
+		self.think_timer -= timestep;
+		let id = match world.vehicle(self.pilot) {
+			Some(id) => id,
+			None => return vec![],
+		};
+		let (own, ship, hull) = match (world.ships.get(id), Craft::of(world, id), world.healths.get(id)) {
+			(Some(own), Some(ship), Some(health)) if health.alive() => (own, ship, health.hull),
+			_ => return vec![],
+		};
+		if self.think_timer > 0.0 {
+			return vec![];
+		}
+		self.think_timer = self.difficulty.reaction;
+
+		let params = &world.ship_params;
+		let class = world.class_of(id);
+		let target = self.target(world, id, &ship);
+		let fleeing = hull < class.hull * FLEE_HULL;
+
+		let mut steer = match target {
+			Some(target) if fleeing => flee(&ship, target.pos, params.max_speed),
+			Some(target) => seek(&ship, self.aim_point(&ship, &target, params.projectile_speed), params.max_speed),
+			//With nobody to fight, bots head for a capture point their team doesn't hold.
+			None => match world.capture.as_ref().filter(|point| point.owner.is_none() || point.owner != world.team_of(id)) {
+				Some(point) if !point.contains(ship.pos) => seek(&ship, point.pos, params.max_speed),
+				_ => self.wander(&ship, params.max_speed),
+			},
+		};
+		steer = add(steer, avoid_asteroids(&ship, class.size, world));
+		steer = add(steer, avoid_edges(&ship, &world.arena, params.max_speed));
+		if let Some(zone) = world.zone.as_ref().filter(|zone| !zone.contains(ship.pos)) {
+			steer = add(steer, scale(seek(&ship, zone.current().0, params.max_speed), 2.0));
+		}
+
+		let heading = steer.1.atan2(steer.0).to_degrees();
+		let turn = angle_between(ship.angle, heading);
+		let speed = length(ship.vel);
+
+		let aim_error = target.map_or(180.0, |target| {
+			let aim = sub(self.aim_point(&ship, &target, params.projectile_speed), ship.pos);
+			angle_between(ship.angle, aim.1.atan2(aim.0).to_degrees()).abs()
+		});
+
+		let controls = Controls {
+			turning : if turn.abs() < 5.0 { 0 } else { turn.signum() as i8 },
+			strafing : 0,
+			thrusting : turn.abs() < 30.0 && length(steer) > 0.1,
+			braking : turn.abs() > 120.0 && speed > 0.3,
+			firing : !fleeing && aim_error < self.difficulty.aim_tolerance && own.inventory.ammo >= 1.0,
+			shielding : fleeing,
+		};
+		controls.actions_for(own)
+	}
+
+	fn target(&self, world : &World, own : EntityId, ship : &Craft) -> Option<Craft> {
+		let team = world.team_of(own);
+		world.ships.iter()
+			//Idle ships nobody flies aren't worth the ammo.
+			.filter(|(id, other)| *id != own && other.pilot.is_some() && world.alive(*id) && other.protection <= 0.0)
+			.filter(|(id, _)| team.is_none() || world.team_of(*id) != team)
+			.filter_map(|(id, _)| Craft::of(world, id))
+			.map(|other| (distance(ship.pos, other.pos), other))
+			.filter(|(distance, _)| *distance < self.difficulty.sight)
+			.min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+			.map(|(_, other)| other)
+	}
+
+	fn aim_point(&self, ship : &Craft, target : &Craft, projectile_speed : f32) -> (f32, f32) {
+		if !self.difficulty.lead || projectile_speed <= 0.0 {
+			return target.pos;
+		}
+		let flight = distance(ship.pos, target.pos) / projectile_speed;
+		add(target.pos, scale(sub(target.vel, ship.vel), flight))
+	}
+
+	//Drifts the heading a little every decision, so idle bots roam instead of circling.
+	fn wander(&mut self, ship : &Craft, max_speed : f32) -> (f32, f32) {
+		self.wander += self.rng.gen_range(-30.0, 30.0);
+		let (sin, cos) = (ship.angle + self.wander.max(-90.0).min(90.0)).to_radians().sin_cos();
+		self.wander *= 0.8;
+		scale((cos, sin), max_speed * 0.5)
+	}
+}
+
+fn seek(ship : &Craft, target : (f32, f32), max_speed : f32) -> (f32, f32) {
+	let desired = scale(normalize(sub(target, ship.pos)), max_speed);
+	sub(desired, ship.vel)
+}
+
+fn flee(ship : &Craft, threat : (f32, f32), max_speed : f32) -> (f32, f32) {
+	let desired = scale(normalize(sub(ship.pos, threat)), max_speed);
+	sub(desired, ship.vel)
+}
+
+//Pushes away from every asteroid close enough to hit soon, harder the closer it is.
+fn avoid_asteroids(ship : &Craft, radius : f32, world : &World) -> (f32, f32) {
+	let ahead = add(ship.pos, scale(ship.vel, 0.5));
+	world.asteroids.iter().fold((0.0, 0.0), |steer, (id, asteroid)| {
+		let offset = sub(ahead, world.asteroids.pos(id));
+		let gap = length(offset) - asteroid.radius - radius;
+		if gap < AVOID_RANGE {
+			add(steer, scale(normalize(offset), (AVOID_RANGE - gap.max(0.0)) / AVOID_RANGE * 2.0))
+		} else {
+			steer
+		}
+	})
+}
+
+fn avoid_edges(ship : &Craft, arena : &world::Arena, max_speed : f32) -> (f32, f32) {
+	if arena.edge == Edge::Wrap {
+		return (0.0, 0.0);
+	}
+	let push = |pos : f32, half_size : f32| {
+		let depth = pos.abs() - (half_size - EDGE_MARGIN);
+		if depth > 0.0 { -pos.signum() * depth / EDGE_MARGIN * max_speed } else { 0.0 }
+	};
+	(push(ship.pos.0, arena.half_size.0), push(ship.pos.1, arena.half_size.1))
+}
+
+//Signed difference from `from` to `to` in degrees, in -180 to 180.
+fn angle_between(from : f32, to : f32) -> f32 {
+	(to - from + 180.0).rem_euclid(360.0) - 180.0
+}
+
+fn add(a : (f32, f32), b : (f32, f32)) -> (f32, f32) {
+	(a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a : (f32, f32), b : (f32, f32)) -> (f32, f32) {
+	(a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a : (f32, f32), s : f32) -> (f32, f32) {
+	(a.0 * s, a.1 * s)
+}
+
+fn length(a : (f32, f32)) -> f32 {
+	(a.0 * a.0 + a.1 * a.1).sqrt()
+}
+
+fn distance(a : (f32, f32), b : (f32, f32)) -> f32 {
+	length(sub(a, b))
+}
+
+fn normalize(a : (f32, f32)) -> (f32, f32) {
+	let len = length(a);
+	if len > 0.0 { scale(a, 1.0 / len) } else { (0.0, 0.0) }
+}
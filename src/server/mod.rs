@@ -1,16 +1,184 @@
 mod state;
+mod admin;
+mod bot;
+mod score;
+mod rules;
 
 use crate::utils;
+use crate::world;
 
-pub fn server() {
+pub const AUTOSAVE_PATH  : &str = "autosave.sav";
+pub const MATCH_LOG_PATH : &str = "matches.jsonl";
 
-	let mut server = state::Server::new();
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+	pub name         : String,
+	pub mode         : String,
+	pub max_players  : usize,
+	pub save_on_exit : Option<String>,
+	pub load         : Option<String>,
+	pub autosave     : Option<f32>,
+	pub ship_params  : Option<String>,
+	pub ships        : Option<String>,
+	pub arena_edge   : Option<world::Edge>,
+	pub arena_size   : Option<f32>,
+	pub seed         : Option<u64>,
+	pub zone         : Option<String>,
+	pub bots         : usize,
+	pub difficulty   : bot::Difficulty,
+	//Match rules from a file, instead of the mode's own.
+	pub rules        : Option<String>,
+	//Override the limits of whichever rules are used.
+	pub score_limit  : Option<u32>,
+	pub time_limit   : Option<f32>,
+	pub match_log    : String,
+	//Overrides the friendly fire share from the ship parameters.
+	pub friendly_fire : Option<f32>,
+}
+
+impl Default for ServerConfig {
+	fn default() -> Self {
+		Self {
+			name         : String::from("surv server"),
+			mode         : String::from("sandbox"),
+			max_players  : 2,
+			save_on_exit : None,
+			load         : None,
+			autosave     : None,
+			ship_params  : None,
+			ships        : None,
+			arena_edge   : None,
+			arena_size   : None,
+			seed         : None,
+			zone         : None,
+			bots         : 0,
+			difficulty   : bot::Difficulty::default(),
+			rules        : None,
+			score_limit  : None,
+			time_limit   : None,
+			match_log    : String::from(MATCH_LOG_PATH),
+			friendly_fire : None,
+		}
+	}
+}
+
+impl ServerConfig {
+	pub fn from_args(args : &[String]) -> Self {
+		let mut config = Self::default();
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--name" => {
+					if let Some(name) = args.next() {
+						config.name = name.clone();
+					}
+				},
+				"--mode" => {
+					if let Some(mode) = args.next() {
+						config.mode = mode.clone();
+					}
+				},
+				"--players" => {
+					if let Some(max_players) = args.next().and_then(|n| n.parse().ok()) {
+						config.max_players = max_players;
+					}
+				},
+				"--save-on-exit" => {
+					config.save_on_exit = args.next().cloned();
+				},
+				"--load" => {
+					config.load = args.next().cloned();
+				},
+				"--autosave" => {
+					config.autosave = args.next().and_then(|secs| secs.parse().ok());
+				},
+				"--ship-params" => {
+					config.ship_params = args.next().cloned();
+				},
+				"--ships" => {
+					config.ships = args.next().cloned();
+				},
+				"--arena" => {
+					config.arena_edge = args.next().and_then(|edge| edge.parse().map_err(|err| println!("{}", err)).ok());
+				},
+				"--arena-size" => {
+					config.arena_size = args.next().and_then(|half_size| half_size.parse().ok());
+				},
+				"--seed" => {
+					config.seed = args.next().and_then(|seed| seed.parse().ok());
+				},
+				"--zone" => {
+					config.zone = args.next().cloned();
+				},
+				"--bots" => {
+					if let Some(bots) = args.next().and_then(|n| n.parse().ok()) {
+						config.bots = bots;
+					}
+				},
+				"--difficulty" => {
+					if let Some(difficulty) = args.next().and_then(|difficulty| difficulty.parse().map_err(|err| println!("{}", err)).ok()) {
+						config.difficulty = difficulty;
+					}
+				},
+				"--rules" => {
+					config.rules = args.next().cloned();
+				},
+				"--score-limit" => {
+					config.score_limit = args.next().and_then(|kills| kills.parse().ok());
+				},
+				"--time-limit" => {
+					config.time_limit = args.next().and_then(|secs| secs.parse().ok());
+				},
+				"--friendly-fire" => {
+					config.friendly_fire = args.next().and_then(|share| share.parse().ok());
+				},
+				"--match-log" => {
+					if let Some(path) = args.next() {
+						config.match_log = path.clone();
+					}
+				},
+				_ => {},
+			}
+		}
+		config
+	}
+}
+
+pub fn server(config : ServerConfig) {
+
+	let mut server = state::Server::new(config);
 
-	server.accept(2);
+	let interrupted = server.interrupted.clone();
+	if let Err(err) = ctrlc::set_handler(move || interrupted.store(true, std::sync::atomic::Ordering::SeqCst)) {
+		println!("Unable to listen for shutdown signals: {}", err);
+	}
+
+	admin::spawn_stdin(server.admin_sender.clone());
 
-	while server.online() {
+	#[cfg(unix)]
+	{
+		let token = admin::admin_token();
+		println!("Admin token: {}", token);
+		admin::spawn_socket(admin::SOCKET_PATH, token, server.admin_sender.clone());
+	}
+
+	for _ in 0..server.config.bots {
+		server.add_bot(server.config.difficulty);
+	}
+
+	server.listen();
+
+	while server.running {
 		server.process();
 	}
 
+	#[cfg(unix)]
+	{
+		let _ = std::fs::remove_file(admin::SOCKET_PATH);
+		if let Some(dir) = std::path::Path::new(admin::SOCKET_PATH).parent() {
+			let _ = std::fs::remove_dir(dir);
+		}
+	}
+
 }
 
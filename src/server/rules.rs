@@ -0,0 +1,106 @@
+use serde_derive::*;
+
+use crate::world::MAX_TEAMS;
+
+//What teams score for. Free for all rounds always score kills.
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Objective {
+	Kills,
+	//A second for every second the team holds the capture point.
+	Capture,
+}
+
+impl Default for Objective {
+	fn default() -> Self {
+		Objective::Kills
+	}
+}
+
+//How a game mode runs its rounds, from the lobby to the scoreboard.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct MatchRules {
+	//Players the lobby waits for before the countdown, counting bots. At least one human is always needed.
+	pub min_players : usize,
+	//Whether every online player has to ready up, rather than the countdown starting as soon as there are enough.
+	pub ready_check : bool,
+	//Seconds the world stands still before a round, once everyone is in a fresh ship.
+	pub countdown   : f32,
+	//A round ends when somebody reaches the score limit or the time limit runs out, whichever comes first.
+	pub score_limit : Option<u32>,
+	pub time_limit  : Option<f32>,
+	//Seconds the scoreboard stays up before the next lobby.
+	pub scoreboard  : f32,
+	//How many teams players are split into, none for free for all. Limits then apply to team scores.
+	#[serde(default)]
+	pub teams       : usize,
+	#[serde(default)]
+	pub objective   : Objective,
+}
+
+impl MatchRules {
+	//Rules files can ask for anything, but there are only so many team colours.
+	pub fn check(self) -> Result<Self, String> {
+		if self.teams > MAX_TEAMS {
+			return Err(format!("{} teams asked for, but at most {} are supported", self.teams, MAX_TEAMS));
+		}
+		Ok(self)
+	}
+
+	//Sandboxes start as soon as somebody joins and never end on their own.
+	pub fn for_mode(mode : &str) -> Self {
+		match mode {
+			"deathmatch" => Self {
+				min_players : 2,
+				ready_check : true,
+				countdown : 5.0,
+				score_limit : Some(20),
+				time_limit : Some(600.0),
+				scoreboard : 10.0,
+				teams : 0,
+				objective : Objective::Kills,
+			},
+			"tdm" => Self {
+				min_players : 2,
+				ready_check : true,
+				countdown : 5.0,
+				score_limit : Some(30),
+				time_limit : Some(600.0),
+				scoreboard : 10.0,
+				teams : 2,
+				objective : Objective::Kills,
+			},
+			"capture" => Self {
+				min_players : 2,
+				ready_check : true,
+				countdown : 5.0,
+				score_limit : Some(120),
+				time_limit : Some(600.0),
+				scoreboard : 10.0,
+				teams : 2,
+				objective : Objective::Capture,
+			},
+			"royale" => Self {
+				min_players : 2,
+				ready_check : true,
+				countdown : 10.0,
+				score_limit : None,
+				//A while after the default zone has closed completely.
+				time_limit : Some(300.0),
+				scoreboard : 15.0,
+				teams : 0,
+				objective : Objective::Kills,
+			},
+			_ => Self {
+				min_players : 0,
+				ready_check : false,
+				countdown : 0.0,
+				score_limit : None,
+				time_limit : None,
+				scoreboard : 10.0,
+				teams : 0,
+				objective : Objective::Kills,
+			},
+		}
+	}
+}
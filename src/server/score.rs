@@ -0,0 +1,163 @@
+use std::io::Write;
+
+use crate::comms::{Kill, PlayerStats, TeamScore};
+use crate::world::{World, WorldEvent, TickEvent, EntityId};
+use super::rules::Objective;
+
+//Stats for everyone who played this round, kept on the server only and keyed by pilot.
+#[derive(Clone, Debug)]
+pub struct Scoreboard {
+	pub players   : Vec<PlayerStats>,
+	//Kills or seconds holding the capture point for each team, empty outside team modes.
+	pub teams     : Vec<f32>,
+	pub objective : Objective,
+	//Seconds since the round started.
+	pub elapsed   : f32,
+}
+
+impl Scoreboard {
+	pub fn new(teams : usize, objective : Objective) -> Self {
+		Self {
+			players : vec![],
+			teams : vec![0.0; teams],
+			objective,
+			elapsed : 0.0,
+		}
+	}
+
+	pub fn join(&mut self, pilot : EntityId, name : String, bot : bool) {
+		self.players.push(PlayerStats {
+			name,
+			pilot,
+			bot,
+			team : None,
+			kills : 0,
+			deaths : 0,
+			damage : 0.0,
+			survival : 0.0,
+		});
+	}
+
+	pub fn update(&mut self, world : &World, timestep : f32) {
+		self.elapsed += timestep;
+		for player in &mut self.players {
+			let alive = world.alive(world.controlled(player.pilot));
+			if alive {
+				player.survival += timestep;
+			}
+		}
+		if self.objective == Objective::Capture {
+			if let Some(score) = world.capture.as_ref().and_then(|point| point.owner).and_then(|team| self.teams.get_mut(team)) {
+				*score += timestep;
+			}
+		}
+	}
+
+	//Credits hits and kills to whoever was flying the ships involved, returning the kill feed for this update.
+	pub fn record(&mut self, world : &World, events : &[TickEvent]) -> Vec<Kill> {
+		let pilot_of = |ship : EntityId| world.ships.get(ship).and_then(|ship| ship.pilot);
+		let enemies = |a : EntityId, b : EntityId| a != b && (world.team_of(b).is_none() || world.team_of(a) != world.team_of(b));
+		let mut kills = vec![];
+		for event in events {
+			match event.event {
+				//Damage to teammates, which friendly fire can allow, doesn't count.
+				WorldEvent::Hit { ship, by : Some(by), damage } if enemies(by, ship) => {
+					if let Some(player) = pilot_of(by).and_then(|pilot| self.player_mut(pilot)) {
+						player.damage += damage;
+					}
+				},
+				WorldEvent::Died { ship, killer } => {
+					let victim = match pilot_of(ship).and_then(|pilot| self.player_mut(pilot)) {
+						Some(player) => {
+							player.deaths += 1;
+							player.name.clone()
+						},
+						None => format!("ship {}", ship),
+					};
+					//Ships that take out themselves or a teammate don't score for it.
+					let killer = killer.filter(|killer| *killer != ship).map(|killer| {
+						let killer_team = world.team_of(killer);
+						let scored = enemies(killer, ship);
+						if scored && self.objective == Objective::Kills {
+							if let Some(score) = killer_team.and_then(|team| self.teams.get_mut(team)) {
+								*score += 1.0;
+							}
+						}
+						match pilot_of(killer).and_then(|pilot| self.player_mut(pilot)) {
+							Some(player) => {
+								if scored {
+									player.kills += 1;
+								}
+								player.name.clone()
+							},
+							None => format!("ship {}", killer),
+						}
+					});
+					kills.push(Kill { killer, victim });
+				},
+				_ => {},
+			}
+		}
+		kills
+	}
+
+	//The best team's score in team modes, otherwise the most kills anyone has.
+	pub fn top_score(&self) -> f32 {
+		if self.teams.is_empty() {
+			self.players.iter().map(|player| player.kills).max().unwrap_or(0) as f32
+		} else {
+			self.teams.iter().cloned().fold(0.0, f32::max)
+		}
+	}
+
+	//Most kills first, ties going to fewer deaths and then more damage. Teams are ranked by their score.
+	pub fn standings(&self, world : &World) -> (Vec<PlayerStats>, Vec<TeamScore>) {
+		let mut players = self.players.clone();
+		for player in &mut players {
+			player.team = world.pilots.get(player.pilot).and_then(|pilot| pilot.team).and_then(|team| world.teams.get(team)).map(|team| team.name.clone());
+		}
+		players.sort_by(|a, b| b.kills.cmp(&a.kills)
+			.then(a.deaths.cmp(&b.deaths))
+			.then(b.damage.partial_cmp(&a.damage).unwrap_or(std::cmp::Ordering::Equal)));
+
+		let mut teams = world.teams.iter().zip(&self.teams)
+			.map(|(team, score)| TeamScore { name : team.name.clone(), score : *score })
+			.collect::<Vec<_>>();
+		teams.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+		(players, teams)
+	}
+
+	//Starts a new round with everyone still on the board.
+	pub fn reset(&mut self) {
+		self.elapsed = 0.0;
+		for score in &mut self.teams {
+			*score = 0.0;
+		}
+		for player in &mut self.players {
+			player.kills = 0;
+			player.deaths = 0;
+			player.damage = 0.0;
+			player.survival = 0.0;
+		}
+	}
+
+	fn player_mut(&mut self, pilot : EntityId) -> Option<&mut PlayerStats> {
+		self.players.iter_mut().find(|player| player.pilot == pilot)
+	}
+}
+
+//Appends one finished round to the match log, a json object per line so the file can be read as it grows.
+pub fn log_match(path : &str, server : &str, mode : &str, reason : &str, duration : f32, standings : &[PlayerStats], teams : &[TeamScore]) -> std::io::Result<()> {
+	let ended = std::time::UNIX_EPOCH.elapsed().map(|time| time.as_secs()).unwrap_or(0);
+	let entry = serde_json::json!({
+		"ended" : ended,
+		"server" : server,
+		"mode" : mode,
+		"reason" : reason,
+		"duration" : duration,
+		"standings" : standings,
+		"teams" : teams,
+	});
+	let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+	writeln!(file, "{}", entry)
+}
@@ -1,27 +1,55 @@
 use std::net;
 use std::thread;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use comms::*;
 
 use crate::world;
 use crate::comms;
 use super::utils;
+use super::admin;
+use super::bot;
+use super::score;
+use super::rules;
+use super::ServerConfig;
+
+const PING_INTERVAL  : f32 = 1.0;
+const CAPTURE_RADIUS : f32 = 1.5;
 
 pub struct Server {
+	pub config          : ServerConfig,
 	pub ip              : net::IpAddr,
 	pub listener        : net::TcpListener,
+	pub query_socket    : net::UdpSocket,
 	pub world           : world::World,
 	pub client_handlers : Vec<thread::JoinHandle<()>>,
 	pub clients         : Vec<comms::ClientComm>,
+	pub bots            : Vec<bot::Bot>,
+	pub scores          : score::Scoreboard,
+	pub rules           : rules::MatchRules,
+	pub phase           : MatchPhase,
+	//Seconds left in the countdown or on the scoreboard.
+	pub phase_timer     : f32,
 	pub sender          : mpsc::Sender<(usize, comms::TimestampedAction)>,
 	pub receiver        : mpsc::Receiver<(usize, comms::TimestampedAction)>,
+	pub admin_sender    : mpsc::Sender<admin::AdminRequest>,
+	pub admin_receiver  : mpsc::Receiver<admin::AdminRequest>,
+	pub banned          : Vec<net::IpAddr>,
+	pub paused          : bool,
+	pub running         : bool,
+	pub interrupted     : Arc<AtomicBool>,
+	pub send_rate       : f32,
 	pub timestep        : utils::Timer,
 	pub authorative_ts  : utils::Timer,
+	pub ping_ts         : utils::Timer,
+	pub autosave_ts     : utils::Timer,
+	pub uptime          : utils::Timer,
 }
 
 impl Server {
-	pub fn new() -> Self {
+	pub fn new(config : ServerConfig) -> Self {
 		let (sender, receiver) = mpsc::channel();
+		let (admin_sender, admin_receiver) = mpsc::channel();
 
 		let server_ip = net::IpAddr::V4(net::Ipv4Addr::new(0,0,0,0));
 
@@ -29,27 +57,123 @@ impl Server {
 
 		let listener = net::TcpListener::bind(socket_addr).unwrap_or_else(|_| panic!("unable to listen on {}", socket_addr));
 
+		let query_addr = net::SocketAddr::new(server_ip, utils::QUERY_PORT);
+
+		let query_socket = net::UdpSocket::bind(query_addr).unwrap_or_else(|_| panic!("unable to listen on {}", query_addr));
+		query_socket.set_nonblocking(true).unwrap();
+
+		let mut world = match &config.load {
+			Some(path) => {
+				let mut world = world::World::load(path).unwrap_or_else(|err| panic!("unable to load {}: {}", path, err));
+				world.clear_pilots();
+				println!("Loaded world from {}", path);
+				world
+			},
+			None => world::World::new(),
+		};
+
+		if let Some(path) = &config.ship_params {
+			let params = std::fs::read_to_string(path).map_err(|err| err.to_string())
+				.and_then(|json| serde_json::from_str(&json).map_err(|err| err.to_string()))
+				.unwrap_or_else(|err| panic!("unable to load ship parameters from {}: {}", path, err));
+			world.ship_params = params;
+		}
+
+		//Class files are authoritative, so a loaded world flies whatever classes are on disk now.
+		match world::load_classes(config.ships.as_deref().unwrap_or(world::CLASSES_DIR)) {
+			Ok(classes) => world.classes = classes,
+			Err(err) if config.ships.is_none() => println!("Using the built in ship class, {}", err),
+			Err(err) => panic!("unable to load ship classes from {}", err),
+		}
+		println!("Ship classes: {}", world.classes.iter().map(|class| class.name.as_str()).collect::<Vec<_>>().join(", "));
+
+		if let Some(edge) = config.arena_edge {
+			world.arena.edge = edge;
+		}
+		if let Some(half_size) = config.arena_size {
+			world.arena.half_size = (half_size, half_size);
+		}
+
+		//A loaded world keeps its asteroids unless a new seed is asked for.
+		if config.load.is_none() || config.seed.is_some() {
+			let seed = config.seed.unwrap_or(world.asteroids.state.seed);
+			world.reseed(seed);
+			println!("Asteroid field seed: {}", seed);
+		}
+
+		//A loaded royale match carries on with the zone it was saved with.
+		if config.mode == "royale" && world.zone.is_none() {
+			let schedule = match &config.zone {
+				Some(path) => std::fs::read_to_string(path).map_err(|err| err.to_string())
+					.and_then(|json| serde_json::from_str(&json).map_err(|err| err.to_string()))
+					.unwrap_or_else(|err| panic!("unable to load zone schedule from {}: {}", path, err)),
+				None => world::zone::default_schedule(),
+			};
+			world.zone = Some(world::Zone::new(schedule, world.arena.half_size, world.asteroids.state.seed));
+		}
+
+		let mut rules = match &config.rules {
+			Some(path) => std::fs::read_to_string(path).map_err(|err| err.to_string())
+				.and_then(|json| serde_json::from_str::<rules::MatchRules>(&json).map_err(|err| err.to_string()))
+				.and_then(rules::MatchRules::check)
+				.unwrap_or_else(|err| panic!("unable to load match rules from {}: {}", path, err)),
+			None => rules::MatchRules::for_mode(&config.mode),
+		};
+		if config.score_limit.is_some() {
+			rules.score_limit = config.score_limit;
+		}
+		if config.time_limit.is_some() {
+			rules.time_limit = config.time_limit;
+		}
+
+		//Teams and the capture point follow the rules, whatever a loaded world was playing.
+		world.teams = world::default_teams(rules.teams);
+		world.capture = match rules.objective {
+			rules::Objective::Capture if rules.teams > 0 => world.capture.take().or_else(|| Some(world::CapturePoint::new((0.0, 0.0), CAPTURE_RADIUS))),
+			_ => None,
+		};
+		if let Some(share) = config.friendly_fire {
+			world.ship_params.friendly_fire = share;
+		}
+
 		Self {
 			ip : utils::get_public_ip(),
-			world : world::World::new(),
+			world,
 			client_handlers : vec![],
 			clients : vec![],
+			bots : vec![],
+			scores : score::Scoreboard::new(rules.teams, rules.objective),
+			rules,
+			phase : MatchPhase::Lobby,
+			phase_timer : 0.0,
+			banned : vec![],
+			paused : false,
+			running : true,
+			interrupted : Arc::new(AtomicBool::new(false)),
+			send_rate : 20.0,
 			timestep : utils::Timer::new(),
 			authorative_ts : utils::Timer::new(),
+			ping_ts : utils::Timer::new(),
+			autosave_ts : utils::Timer::new(),
+			uptime : utils::Timer::new(),
+			config,
 			listener,
+			query_socket,
 			sender,
 			receiver,
+			admin_sender,
+			admin_receiver,
 		}
 	}
 
-	pub fn handle_client(player_id : usize, mut client : ClientComm, sender : mpsc::Sender<(usize, comms::TimestampedAction)>) {
+	pub fn handle_client(player_id : usize, pilot : world::EntityId, mut client : ClientComm, sender : mpsc::Sender<(usize, comms::TimestampedAction)>) {
 
 		const DISCONNECT : TimestampedAction = TimestampedAction {
 			timestamp : 0.0,
 			action : Action::Disconnect
 		};
 
-		client.authorative_send(Perception::ID(player_id));
+		client.authorative_send(Perception::ID(pilot));
 
 		loop {
 			match client.recv() {
@@ -60,7 +184,7 @@ impl Server {
 				Err(bincode::ErrorKind::Io(err)) if err.kind() == std::io::ErrorKind::WouldBlock => (),
 				Err(bincode::ErrorKind::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => (),
 				Err(err) => {
-					sender.send((player_id, DISCONNECT)).expect("Server already closed.");
+					let _ = sender.send((player_id, DISCONNECT));
 					println!("Error '{:?}' from '{:?}'.", err, client);
 					break;
 				},
@@ -71,64 +195,513 @@ impl Server {
 
 	}
 
-	pub fn accept(&mut self, n : usize) {
+	pub fn listen(&mut self) {
 		println!("Listening on {:?}", net::SocketAddr::new(self.ip, utils::SERVER_PORT));
-		for stream in self.listener.incoming() {
-			match stream {
-				Ok(client) => {
+		self.listener.set_nonblocking(true).unwrap();
+		self.timestep.reset();
+	}
 
-					println!("New connection: {:?}", client);
+	//Takes in whoever is waiting to connect while there's room, whatever the phase. Latecomers join the round in progress.
+	fn accept_pending(&mut self) {
+		while self.clients.iter().filter(|x| x.online).count() < self.config.max_players {
+			match self.listener.accept() {
+				Ok((client, _)) => self.connect(client),
+				Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+				Err(err) => {
+					println!("Unable to accept a connection: {}", err);
+					break;
+				},
+			}
+		}
+	}
+
+	pub fn connect(&mut self, client : net::TcpStream) {
+		if client.peer_addr().map_or(false, |addr| self.banned.contains(&addr.ip())) {
+			println!("Refused banned connection: {:?}", client);
+			let _ = client.shutdown(net::Shutdown::Both);
+			return;
+		}
 
-					let player_client = comms::ClientComm::new(client);
+		println!("New connection: {:?}", client);
 
-					let cloned_sender = self.sender.clone();
-					let cloned_client = player_client.clone();
-					let player_id = self.client_handlers.len();
-					let join_handle = thread::spawn(move || {
-						Self::handle_client(player_id, cloned_client, cloned_sender);
-					});
-					self.client_handlers.push(join_handle);
-					self.clients.push(player_client);
-					self.world.ships.push(world::Ship::new());
+		let ship = self.free_ship();
+		let pilot = self.world.spawn_pilot(ship);
+		let team = self.balanced_team();
+		self.world.set_team(pilot, team);
+		let player_client = comms::ClientComm::new(client, pilot);
+		self.scores.join(pilot, format!("player {}", self.clients.len()), false);
 
+		let cloned_sender = self.sender.clone();
+		let cloned_client = player_client.clone();
+		let player_id = self.client_handlers.len();
+		let join_handle = thread::spawn(move || {
+			Self::handle_client(player_id, pilot, cloned_client, cloned_sender);
+		});
+		self.client_handlers.push(join_handle);
+		self.clients.push(player_client);
+	}
+
+	//Reuses the first ship nobody flies, like those of a loaded world or a removed bot, before adding a new one.
+	fn free_ship(&mut self) -> world::EntityId {
+		let free = self.world.ships.iter().find(|(_, ship)| ship.pilot.is_none()).map(|(id, _)| id);
+		match free {
+			Some(id) => id,
+			None => {
+				let class = self.world.classes[0].name.clone();
+				self.world.add_ship(&class)
+			},
+		}
+	}
+
+	//Players pick a class as they join, or while waiting to respawn, never mid-fight.
+	fn choose_class(&mut self, player_id : usize, name : &str) -> Result<(), String> {
+		let ship = match self.world.vehicle(self.clients[player_id].pilot) {
+			Some(ship) => ship,
+			None => return Err(String::from("board a ship before picking its class")),
+		};
+		let ready = !self.world.alive(ship) || self.world.ships.get(ship).map_or(false, |ship| ship.protection > 0.0);
+		if !self.world.classes.iter().any(|class| class.name == name) {
+			let names = self.world.classes.iter().map(|class| class.name.as_str()).collect::<Vec<_>>();
+			return Err(format!("unknown ship class '{}', expected one of {}", name, names.join(", ")));
+		}
+		if !ready {
+			return Err(String::from("ship classes can only be changed when joining or while respawning"));
+		}
+		self.world.set_class(ship, name);
+		Ok(())
+	}
+
+	//The team with the fewest pilots, the first of them on a tie, or none outside team modes.
+	fn balanced_team(&self) -> Option<usize> {
+		(0..self.world.teams.len()).min_by_key(|team| self.world.pilots.iter().filter(|(_, pilot)| pilot.team == Some(*team)).count())
+	}
+
+	//Like classes, teams can be switched before the round starts or while respawning, never mid-fight.
+	fn choose_team(&mut self, player_id : usize, name : &str) -> Result<(), String> {
+		let pilot = self.clients[player_id].pilot;
+		let team = match self.world.teams.iter().position(|team| team.name == name) {
+			Some(team) => team,
+			None if self.world.teams.is_empty() => return Err(String::from("there are no teams in this mode")),
+			None => {
+				let names = self.world.teams.iter().map(|team| team.name.as_str()).collect::<Vec<_>>();
+				return Err(format!("unknown team '{}', expected one of {}", name, names.join(", ")));
+			},
+		};
+		let waiting = self.phase == MatchPhase::Lobby || self.phase == MatchPhase::Countdown;
+		let respawning = self.world.vehicle(pilot).map_or(false, |ship| !self.world.alive(ship) || self.world.ships.get(ship).map_or(false, |ship| ship.protection > 0.0));
+		if !waiting && !respawning {
+			return Err(String::from("teams can only be changed before the round or while respawning"));
+		}
+		self.world.set_team(pilot, Some(team));
+		Ok(())
+	}
+
+	pub fn add_bot(&mut self, difficulty : bot::Difficulty) -> world::EntityId {
+		let ship = self.free_ship();
+		let pilot = self.world.spawn_pilot(ship);
+		let team = self.balanced_team();
+		self.world.set_team(pilot, team);
+		let rng = self.world.rng.fork();
+		self.bots.push(bot::Bot::new(pilot, difficulty, rng));
+		let number = self.scores.players.iter().filter(|player| player.bot).count() + 1;
+		self.scores.join(pilot, format!("bot {}", number), true);
+		ship
+	}
+
+	//The bot's pilot is taken out and its ship left idle for the next player or bot to take over.
+	pub fn remove_bot(&mut self, ship : world::EntityId) -> Option<world::EntityId> {
+		let world = &self.world;
+		let index = self.bots.iter().position(|bot| world.vehicle(bot.pilot) == Some(ship))?;
+		let pilot = self.bots.remove(index).pilot;
+		let ship = self.world.vehicle(pilot);
+		self.world.remove_pilot(pilot);
+		self.scores.players.retain(|player| player.pilot != pilot);
+		ship
+	}
+
+	//The lobby needs somebody to play with, enough players counting bots, and everyone ready if the mode checks.
+	fn lobby_ready(&self) -> bool {
+		let online = self.clients.iter().filter(|x| x.online).collect::<Vec<_>>();
+		!online.is_empty()
+			&& online.len() + self.bots.len() >= self.rules.min_players
+			&& (!self.rules.ready_check || online.iter().all(|client| client.ready))
+	}
+
+	//Rounds end on whichever limit is reached first, and never without one.
+	fn round_over(&self) -> Option<&'static str> {
+		if self.rules.score_limit.map_or(false, |limit| self.scores.top_score() >= limit as f32) {
+			Some("score limit")
+		} else if self.rules.time_limit.map_or(false, |limit| self.scores.elapsed >= limit) {
+			Some("time limit")
+		} else {
+			None
+		}
+	}
+
+	//Moves the match on once the rules for the current phase are met.
+	fn advance_phase(&mut self, timestep : f32) {
+		self.phase_timer -= timestep;
+		let deserted = self.clients.iter().all(|x| !x.online);
+		let next = match self.phase {
+			MatchPhase::Lobby if self.lobby_ready() => MatchPhase::Countdown,
+			MatchPhase::Countdown | MatchPhase::Playing if deserted => {
+				println!("Everyone left, back to the lobby");
+				MatchPhase::Lobby
+			},
+			MatchPhase::Countdown if self.phase_timer <= 0.0 => MatchPhase::Playing,
+			MatchPhase::Playing => match self.round_over() {
+				Some(reason) => {
+					self.end_round(reason);
+					MatchPhase::RoundOver
 				},
-				Err(e) => {
-					panic!("Error: {}", e);
+				None => return,
+			},
+			MatchPhase::RoundOver if self.phase_timer <= 0.0 => MatchPhase::Lobby,
+			_ => return,
+		};
+		self.enter_phase(next);
+	}
+
+	fn enter_phase(&mut self, phase : MatchPhase) {
+		println!("Match phase: {:?}", phase);
+		self.phase = phase;
+		match phase {
+			MatchPhase::Lobby => {
+				for client in self.clients.iter_mut() {
+					client.ready = false;
 				}
+			},
+			MatchPhase::Countdown => {
+				self.phase_timer = self.rules.countdown;
+				self.world.reset_round();
+				self.scores.reset();
+			},
+			MatchPhase::Playing => {},
+			MatchPhase::RoundOver => self.phase_timer = self.rules.scoreboard,
+		}
+		self.send_phase();
+	}
+
+	fn send_phase(&self) {
+		let remaining = match self.phase {
+			MatchPhase::Lobby => None,
+			MatchPhase::Playing => self.rules.time_limit.map(|limit| (limit - self.scores.elapsed).max(0.0)),
+			MatchPhase::Countdown | MatchPhase::RoundOver => Some(self.phase_timer.max(0.0)),
+		};
+		for client in self.clients.iter().filter(|x| x.online) {
+			client.authorative_send(Perception::Phase(self.phase, remaining));
+		}
+	}
+
+	//Shows everyone the scoreboard and logs the round.
+	fn end_round(&mut self, reason : &str) {
+		let (standings, teams) = self.scores.standings(&self.world);
+		match (teams.first(), standings.first()) {
+			(Some(team), _) => println!("Round over on the {}, won by team {} with {:.0}", reason, team.name, team.score),
+			(None, Some(winner)) => println!("Round over on the {}, won by {} with {} kill(s)", reason, winner.name, winner.kills),
+			(None, None) => println!("Round over on the {}, nobody played", reason),
+		}
+		for client in self.clients.iter().filter(|x| x.online) {
+			client.authorative_send(Perception::Scoreboard(standings.clone(), teams.clone()));
+		}
+		if let Err(err) = score::log_match(&self.config.match_log, &self.config.name, &self.config.mode, reason, self.scores.elapsed, &standings, &teams) {
+			println!("Unable to write to the match log {}: {}", self.config.match_log, err);
+		}
+	}
+
+	pub fn answer_queries(&mut self) {
+		let mut buff = [0u8; 64];
+		while let Ok((n, addr)) = self.query_socket.recv_from(&mut buff) {
+			if &buff[..n] != comms::QUERY_REQUEST {
+				continue;
 			}
-			if self.client_handlers.len() >= n {
-				break;
+
+			let status = ServerStatus {
+				name : self.config.name.clone(),
+				mode : self.config.mode.clone(),
+				players : self.clients.iter().enumerate().filter(|(_, client)| client.online).map(|(id, client)| {
+					PlayerStatus {
+						id,
+						ping : client.ping,
+					}
+				}).collect(),
+				max_players : self.config.max_players,
+				protocol : crate::format::FORMAT_VERSION,
+				uptime : self.uptime.secs(),
+			};
+
+			if let Ok(response) = bincode::serialize(&status) {
+				let _ = self.query_socket.send_to(&response, addr);
 			}
 		}
-		println!("Stopped listening on {:?}", net::SocketAddr::new(self.ip, utils::SERVER_PORT));
-		self.timestep.reset();
+	}
+
+	pub fn handle_admin(&mut self) {
+		while let Ok(request) = self.admin_receiver.try_recv() {
+			let reply = self.admin(request.command);
+			let _ = request.reply.send(reply);
+		}
+	}
+
+	pub fn check_interrupt(&mut self) {
+		if self.running && self.interrupted.load(Ordering::SeqCst) {
+			self.shutdown("the host closed the server");
+		}
 	}
 
 	pub fn process(&mut self) {
+		self.check_interrupt();
+		self.accept_pending();
+
 		let timestep = self.timestep.reset();
-		self.world.update(timestep);
+		let playing = self.phase == MatchPhase::Playing;
+		if !self.paused && self.phase.live() {
+			for bot in &mut self.bots {
+				for action in bot.think(&self.world, timestep) {
+					self.world.process(bot.pilot, &action);
+				}
+			}
+			self.world.update(timestep);
+			self.world.resolve_hits();
+			self.world.collect_pickups();
+			self.world.scatter_pickups();
+			self.world.apply_hazards(timestep);
+			self.world.respawn_ships();
+			if playing {
+				self.scores.update(&self.world, timestep);
+			}
+		}
 
-		while let Ok(action) = self.receiver.try_recv() {
-			self.clients[action.0].timestamp = action.1.timestamp;
+		if !self.world.events.is_empty() {
+			let events = self.world.events.drain();
+			//Warm-up kills don't count and don't make the feed.
+			let kills = if playing { self.scores.record(&self.world, &events) } else { vec![] };
+			for client in self.clients.iter().filter(|x| x.online) {
+				client.authorative_send(Perception::Events(events.clone()));
+				for kill in &kills {
+					client.authorative_send(Perception::Kill(kill.clone()));
+				}
+			}
+		}
+
+		if !self.paused {
+			self.advance_phase(timestep);
+		}
 
+		self.handle_admin();
+		self.answer_queries();
+
+		while let Ok(action) = self.receiver.try_recv() {
 			use Action::*;
 			match action.1.action {
-				Disconnect => self.clients[action.0].disconnect(),
-				act => self.world.process(action.0, &act),
+				Pong(sent) => {
+					let now = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64();
+					self.clients[action.0].ping = ((now - sent) * 1000.0) as f32;
+				},
+				Disconnect => {
+					self.clients[action.0].timestamp = action.1.timestamp;
+					self.drop_client(action.0);
+				},
+				Ready(ready) if self.phase == MatchPhase::Lobby => {
+					self.clients[action.0].ready = ready;
+					let online = self.clients.iter().filter(|x| x.online);
+					let count = online.clone().filter(|client| client.ready).count();
+					let message = format!("Player {} is {}, {}/{} ready", action.0, if ready { "ready" } else { "not ready" }, count, online.count());
+					for client in self.clients.iter().filter(|x| x.online) {
+						client.authorative_send(Perception::Message(message.clone()));
+					}
+				},
+				Ready(_) => {},
+				Message(text) => {
+					let message = format!("Player {}: {}", action.0, text);
+					for client in self.clients.iter().filter(|x| x.online) {
+						client.authorative_send(Perception::Message(message.clone()));
+					}
+				},
+				TeamMessage(text) => {
+					let team_of = |client : &ClientComm| self.world.pilots.get(client.pilot).and_then(|pilot| pilot.team);
+					match team_of(&self.clients[action.0]) {
+						Some(team) => {
+							let message = format!("[{}] Player {}: {}", self.world.teams[team].name, action.0, text);
+							for client in self.clients.iter().filter(|x| x.online && team_of(x) == Some(team)) {
+								client.authorative_send(Perception::Message(message.clone()));
+							}
+						},
+						None => self.clients[action.0].authorative_send(Perception::Message(String::from("You aren't on a team"))),
+					}
+				},
+				ChooseTeam(name) => {
+					let message = match self.choose_team(action.0, &name) {
+						Ok(()) => format!("Now on team {}", name),
+						Err(err) => err,
+					};
+					self.clients[action.0].authorative_send(Perception::Message(message));
+				},
+				ChooseClass(name) => {
+					let message = match self.choose_class(action.0, &name) {
+						Ok(()) => format!("Now flying a {}", name),
+						Err(err) => err,
+					};
+					self.clients[action.0].authorative_send(Perception::Message(message));
+				},
+				act => {
+					self.clients[action.0].timestamp = action.1.timestamp;
+					self.world.process(self.clients[action.0].pilot, &act);
+				},
 			}
 		}
 
-		if self.authorative_ts.secs() > 50./1000. {
+		if self.authorative_ts.secs() > 1. / self.send_rate {
 			self.authorative_ts.reset();
 			for client in self.clients.iter().filter(|x| x.online) {
 				client.authorative_send(Perception::World(self.world.clone()));
 			}
+			self.send_phase();
+		}
+
+		if let Some(interval) = self.config.autosave {
+			if self.autosave_ts.secs() > interval {
+				self.autosave_ts.reset();
+				if let Err(err) = self.world.save(super::AUTOSAVE_PATH) {
+					println!("Unable to autosave to {}: {}", super::AUTOSAVE_PATH, err);
+				}
+			}
+		}
+
+		if self.ping_ts.secs() > PING_INTERVAL {
+			self.ping_ts.reset();
+			let now = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs_f64();
+			for client in self.clients.iter().filter(|x| x.online) {
+				client.authorative_send(Perception::Ping(now));
+			}
+		}
+
+	}
+
+	pub fn admin(&mut self, command : admin::AdminCommand) -> admin::AdminReply {
+		use admin::{AdminCommand::*, AdminReply};
+		match command {
+			Help => AdminReply::ok(admin::AdminCommand::USAGE.to_owned()),
+			Players => {
+				let mut lines = vec![format!("{} player(s), {} bot(s)", self.clients.len(), self.bots.len())];
+				let mut players = vec![];
+				for (id, client) in self.clients.iter().enumerate() {
+					let address = client.stream.stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+					let ship = self.world.vehicle(client.pilot);
+					let class = ship.map_or("", |ship| self.world.class_of(ship).name.as_str());
+					let flying = ship.map_or(String::from("on foot"), |ship| format!("ship {}", ship));
+					lines.push(format!("{}\t{}\t{}\t{}\t{}", id, flying, class, address, if client.online { "online" } else { "offline" }));
+					players.push(serde_json::json!({
+						"id" : id,
+						"pilot" : client.pilot,
+						"ship" : ship,
+						"class" : class,
+						"address" : address,
+						"online" : client.online,
+					}));
+				}
+				for bot in &self.bots {
+					let ship = self.world.vehicle(bot.pilot);
+					let class = ship.map_or("", |ship| self.world.class_of(ship).name.as_str());
+					let flying = ship.map_or(String::from("on foot"), |ship| format!("ship {}", ship));
+					lines.push(format!("bot\t{}\t{}", flying, class));
+					players.push(serde_json::json!({
+						"pilot" : bot.pilot,
+						"ship" : ship,
+						"class" : class,
+						"bot" : true,
+					}));
+				}
+				AdminReply::ok(lines.join("\n")).with_data(serde_json::Value::Array(players))
+			},
+			Kick(id, _) | Ban(id, _) if self.clients.get(id).map_or(true, |client| !client.online) => {
+				AdminReply::err(format!("no online player with id {}", id))
+			},
+			Kick(id, reason) => {
+				self.kick(id, &reason);
+				AdminReply::ok(format!("kicked player {}", id))
+			},
+			Ban(id, reason) => {
+				if let Ok(addr) = self.clients[id].stream.stream.peer_addr() {
+					self.banned.push(addr.ip());
+				}
+				self.kick(id, &reason);
+				AdminReply::ok(format!("banned player {}", id))
+			},
+			Broadcast(message) => {
+				for client in self.clients.iter().filter(|x| x.online) {
+					client.authorative_send(Perception::Message(message.clone()));
+				}
+				AdminReply::ok(format!("sent '{}'", message))
+			},
+			Pause => {
+				self.paused = true;
+				AdminReply::ok(String::from("simulation paused"))
+			},
+			Resume => {
+				self.paused = false;
+				AdminReply::ok(String::from("simulation resumed"))
+			},
+			SendRate(rate) => {
+				self.send_rate = rate;
+				AdminReply::ok(format!("snapshots now sent at {}hz", rate))
+			},
+			AddBot(difficulty) => {
+				let ship = self.add_bot(difficulty);
+				AdminReply::ok(format!("added a bot flying ship {}", ship))
+			},
+			RemoveBot(ship) => {
+				match ship.or_else(|| self.bots.last().and_then(|bot| self.world.vehicle(bot.pilot))) {
+					Some(ship) => match self.remove_bot(ship) {
+						Some(ship) => AdminReply::ok(format!("removed the bot flying ship {}", ship)),
+						None => AdminReply::err(format!("no bot is flying ship {}", ship)),
+					},
+					None => AdminReply::err(String::from("there are no bots to remove")),
+				}
+			},
+			Save(path) => {
+				match self.world.save(&path) {
+					Ok(()) => AdminReply::ok(format!("world saved to {}", path)),
+					Err(err) => AdminReply::err(format!("unable to save to {}: {}", path, err)),
+				}
+			},
+			Shutdown(reason) => {
+				self.shutdown(if reason.is_empty() { "shut down by an administrator" } else { &reason });
+				AdminReply::ok(String::from("shutting down"))
+			},
+		}
+	}
+
+	pub fn shutdown(&mut self, reason : &str) {
+		println!("Shutting down: {}", reason);
+		self.running = false;
+
+		if let Some(path) = &self.config.save_on_exit {
+			match self.world.save(path) {
+				Ok(()) => println!("World saved to {}", path),
+				Err(err) => println!("Unable to save world to {}: {}", path, err),
+			}
+		}
+
+		for client in self.clients.iter_mut().filter(|x| x.online) {
+			client.authorative_send(Perception::Shutdown(reason.to_owned()));
+			client.disconnect();
 		}
+	}
 
+	pub fn kick(&mut self, player_id : usize, reason : &str) {
+		let reason = if reason.is_empty() { "no reason given" } else { reason };
+		self.clients[player_id].authorative_send(Perception::Message(format!("Kicked from server: {}", reason)));
+		self.drop_client(player_id);
 	}
 
-	pub fn online(&self) -> bool {
-		if self.clients.iter().any(|c| c.online) { true } else { false }
+	//Whoever leaves takes their pilot with them, freeing the ship and dropping them from the scoreboard straight away.
+	fn drop_client(&mut self, player_id : usize) {
+		let client = &mut self.clients[player_id];
+		client.disconnect();
+		let pilot = client.pilot;
+		self.world.remove_pilot(pilot);
+		self.scores.players.retain(|player| player.pilot != pilot);
 	}
 
 }
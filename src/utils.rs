@@ -2,6 +2,7 @@ use std::net;
 use std::time;
 
 pub const SERVER_PORT : u16 = 8778;
+pub const QUERY_PORT  : u16 = SERVER_PORT + 1;
 
 pub fn get_public_ip() -> net::IpAddr {
 	let ip_str = reqwest::blocking::get("https://www.sfml-dev.org/ip-provider.php")
@@ -0,0 +1,105 @@
+use serde_derive::*;
+
+use crate::reng::types::*;
+use crate::client::types::Instance2D;
+
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Debug)]
+pub enum Edge {
+	Walls,
+	Wrap,
+	//Ships outside the arena take this much damage per second.
+	Hazard { damage : f32 },
+}
+
+impl std::str::FromStr for Edge {
+	type Err = String;
+
+	fn from_str(edge : &str) -> Result<Self, Self::Err> {
+		match edge {
+			"walls" => Ok(Edge::Walls),
+			"wrap" => Ok(Edge::Wrap),
+			"hazard" => Ok(Edge::Hazard { damage : 20.0 }),
+			_ => Err(format!("unknown arena edge '{}', expected walls, wrap or hazard", edge)),
+		}
+	}
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Arena {
+	pub half_size : (f32, f32),
+	pub edge      : Edge,
+}
+
+impl Default for Arena {
+	fn default() -> Self {
+		Self {
+			half_size : (8.0, 8.0),
+			edge : Edge::Walls,
+		}
+	}
+}
+
+impl Arena {
+
+	const WALL_RESTITUTION : f32 = 0.5;
+	const BORDER_WIDTH     : f32 = 0.05;
+
+	pub fn contains(&self, pos : (f32, f32)) -> bool {
+		pos.0.abs() <= self.half_size.0 && pos.1.abs() <= self.half_size.1
+	}
+
+	//Keeps a body inside the arena according to its edge, returning whether it touched a wall.
+	pub fn confine(&self, pos : &mut (f32, f32), vel : &mut (f32, f32), radius : f32) -> bool {
+		match self.edge {
+			Edge::Walls => {
+				let confine_axis = |pos : &mut f32, vel : &mut f32, half_size : f32| {
+					let limit = (half_size - radius).max(0.0);
+					if pos.abs() <= limit {
+						return false;
+					}
+					*pos = pos.signum() * limit;
+					if *vel * pos.signum() > 0.0 {
+						*vel = -*vel * Self::WALL_RESTITUTION;
+					}
+					true
+				};
+				let touched_x = confine_axis(&mut pos.0, &mut vel.0, self.half_size.0);
+				let touched_y = confine_axis(&mut pos.1, &mut vel.1, self.half_size.1);
+				touched_x || touched_y
+			},
+			Edge::Wrap => {
+				let wrap = |x : f32, half_size : f32| (x + half_size).rem_euclid(2.0 * half_size) - half_size;
+				pos.0 = wrap(pos.0, self.half_size.0);
+				pos.1 = wrap(pos.1, self.half_size.1);
+				false
+			},
+			Edge::Hazard { .. } => false,
+		}
+	}
+
+	pub fn render_to(&self, output_buffer : &mut Vec<Instance2D>, text_coords : GLvec4) {
+		let tint = match self.edge {
+			Edge::Walls => GLvec4(0.6, 0.6, 0.7, 1.0),
+			Edge::Wrap => GLvec4(0.1, 0.3, 0.5, 0.5),
+			Edge::Hazard { .. } => GLvec4(0.7, 0.1, 0.1, 0.8),
+		};
+
+		let (w, h) = self.half_size;
+		let border = Self::BORDER_WIDTH;
+		let sides = [
+			((0.0, h), (w + border, border)),
+			((0.0, -h), (w + border, border)),
+			((w, 0.0), (border, h + border)),
+			((-w, 0.0), (border, h + border)),
+		];
+
+		output_buffer.extend(sides.iter().map(|&(center, scale)| {
+			let mut instance = Instance2D::default();
+			instance.color_tint = tint;
+			instance.texture_coords = text_coords;
+			instance.translate = GLvec2(center.0, center.1);
+			instance.scale = GLvec2(scale.0, scale.1);
+			instance
+		}));
+	}
+}
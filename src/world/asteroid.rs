@@ -0,0 +1,234 @@
+use serde_derive::*;
+use serde::{Serialize, Serializer};
+use fnv::FnvHashMap;
+use rand::Rng;
+
+use crate::reng::types::*;
+use crate::client::types::Instance2D;
+use super::collision::Shape;
+use super::rng::WorldRng;
+
+//Asteroids per square unit of the field.
+const DENSITY        : f32 = 0.15;
+const MIN_RADIUS     : f32 = 0.15;
+const MAX_RADIUS     : f32 = 0.8;
+const DRIFTING       : f64 = 0.5;
+const MAX_DRIFT      : f32 = 0.3;
+//Health per unit of radius.
+const TOUGHNESS      : f32 = 100.0;
+//Pieces smaller than this are destroyed outright instead of breaking apart.
+const MIN_FRAGMENT   : f32 = 0.12;
+const FRAGMENT_SPEED : f32 = 0.4;
+//Scrap dropped per unit of radius when an asteroid is destroyed.
+const SCRAP          : f32 = 10.0;
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Asteroid {
+	//Where the asteroid was when the field's clock was at zero, it drifts in a straight line from there.
+	pub origin : (f32, f32),
+	pub vel    : (f32, f32),
+	pub spin   : f32,
+	pub radius : f32,
+	pub health : f32,
+}
+
+impl Asteroid {
+
+	fn new(origin : (f32, f32), vel : (f32, f32), spin : f32, radius : f32) -> Self {
+		Self {
+			origin,
+			vel,
+			spin,
+			radius,
+			health : radius * TOUGHNESS,
+		}
+	}
+
+	pub fn alive(&self) -> bool {
+		self.health > 0.0
+	}
+
+	pub fn shape(&self) -> Shape {
+		Shape::Circle { radius : self.radius }
+	}
+
+	//Asteroids wrap around the field's extent whatever the arena's edges do to ships.
+	pub fn pos(&self, time : f64, extent : (f32, f32)) -> (f32, f32) {
+		(
+			wrap(self.origin.0 as f64 + self.vel.0 as f64 * time, extent.0),
+			wrap(self.origin.1 as f64 + self.vel.1 as f64 * time, extent.1),
+		)
+	}
+
+	pub fn angle(&self, time : f64) -> f32 {
+		(self.spin as f64 * time % 360.0) as f32
+	}
+
+	pub fn scrap(&self) -> f32 {
+		(self.radius * SCRAP).ceil()
+	}
+
+	//The pieces left behind when this asteroid is destroyed at `pos`.
+	fn fragments(&self, pos : (f32, f32), time : f64, extent : (f32, f32), rng : &mut impl Rng) -> Vec<Asteroid> {
+		let radius = self.radius * 0.55;
+		if radius < MIN_FRAGMENT {
+			return vec![];
+		}
+
+		let offset = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
+		(0..2).map(|n| {
+			let (sin, cos) = (offset + n as f32 * std::f32::consts::PI).sin_cos();
+			let vel = (self.vel.0 + cos * FRAGMENT_SPEED, self.vel.1 + sin * FRAGMENT_SPEED);
+			let start = (pos.0 + cos * radius, pos.1 + sin * radius);
+			let origin = (
+				wrap(start.0 as f64 - vel.0 as f64 * time, extent.0),
+				wrap(start.1 as f64 - vel.1 as f64 * time, extent.1),
+			);
+			Asteroid::new(origin, vel, rng.gen_range(-90.0, 90.0), radius)
+		}).collect()
+	}
+
+	pub fn render(&self, text_coords : GLvec4, time : f64, extent : (f32, f32)) -> Instance2D {
+		let pos = self.pos(time, extent);
+		let mut instance = Instance2D::default();
+		instance.color_tint = GLvec4(0.45, 0.4, 0.35, 1.0);
+		instance.texture_coords = text_coords;
+		instance.translate = GLvec2(pos.0, pos.1);
+		//A square of the same area as the collision circle.
+		instance.scale = GLvec2(self.radius * 0.886, self.radius * 0.886);
+		instance.rotation = GLfloat(self.angle(time));
+		instance
+	}
+}
+
+fn wrap(x : f64, half_size : f32) -> f32 {
+	let half_size = half_size as f64;
+	((x + half_size).rem_euclid(2.0 * half_size) - half_size) as f32
+}
+
+//Everything needed to rebuild an asteroid field, which is all that is sent to clients and saved.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct FieldState {
+	pub seed    : u64,
+	pub extent  : (f32, f32),
+	pub time    : f64,
+	//Health left on seeded asteroids that have been shot, zero once destroyed.
+	pub damaged : FnvHashMap<usize, f32>,
+	//Asteroids that can't be regenerated from the seed, like fragments, are sent in full.
+	pub spawned : Vec<Asteroid>,
+}
+
+//Asteroids are indexed with the seeded ones first, followed by the spawned ones.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(from = "FieldState")]
+pub struct AsteroidField {
+	pub state : FieldState,
+	seeded    : Vec<Asteroid>,
+}
+
+impl From<FieldState> for AsteroidField {
+	fn from(state : FieldState) -> Self {
+		let mut seeded = generate(state.seed, state.extent);
+		for (&index, &health) in &state.damaged {
+			if let Some(asteroid) = seeded.get_mut(index) {
+				asteroid.health = health;
+			}
+		}
+		Self { state, seeded }
+	}
+}
+
+impl Serialize for AsteroidField {
+	fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+		self.state.serialize(serializer)
+	}
+}
+
+impl AsteroidField {
+	pub fn new(seed : u64, extent : (f32, f32)) -> Self {
+		Self::from(FieldState {
+			seed,
+			extent,
+			time : 0.0,
+			damaged : FnvHashMap::default(),
+			spawned : vec![],
+		})
+	}
+
+	pub fn update(&mut self, timestep : f32) {
+		self.state.time += timestep as f64;
+	}
+
+	pub fn get(&self, index : usize) -> &Asteroid {
+		if index < self.seeded.len() {
+			&self.seeded[index]
+		} else {
+			&self.state.spawned[index - self.seeded.len()]
+		}
+	}
+
+	fn get_mut(&mut self, index : usize) -> &mut Asteroid {
+		if index < self.seeded.len() {
+			&mut self.seeded[index]
+		} else {
+			&mut self.state.spawned[index - self.seeded.len()]
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (usize, &Asteroid)> {
+		self.seeded.iter().chain(&self.state.spawned).enumerate().filter(|(_, asteroid)| asteroid.alive())
+	}
+
+	pub fn pos(&self, index : usize) -> (f32, f32) {
+		self.get(index).pos(self.state.time, self.state.extent)
+	}
+
+	//Returns the destroyed asteroid and where it was, leaving any fragments in its place.
+	//Destroyed spawned asteroids keep their index until `clear_destroyed`, so collision events stay valid.
+	pub fn damage(&mut self, index : usize, amount : f32, rng : &mut impl Rng) -> Option<(Asteroid, (f32, f32))> {
+		let asteroid = self.get_mut(index);
+		asteroid.health = (asteroid.health - amount).max(0.0);
+		let (health, alive) = (asteroid.health, asteroid.alive());
+		if index < self.seeded.len() {
+			self.state.damaged.insert(index, health);
+		}
+		if alive {
+			return None;
+		}
+
+		let asteroid = self.get(index).clone();
+		let pos = self.pos(index);
+		let fragments = asteroid.fragments(pos, self.state.time, self.state.extent, rng);
+		self.state.spawned.extend(fragments);
+		Some((asteroid, pos))
+	}
+
+	pub fn clear_destroyed(&mut self) {
+		self.state.spawned.retain(Asteroid::alive);
+	}
+
+	pub fn render_to(&self, output_buffer : &mut Vec<Instance2D>, text_coords : GLvec4) {
+		output_buffer.extend(
+			self.iter().map(|(_, asteroid)| asteroid.render(text_coords, self.state.time, self.state.extent))
+		);
+	}
+}
+
+//The same seed and extent always give the same asteroids, so only the seed has to be shared.
+fn generate(seed : u64, extent : (f32, f32)) -> Vec<Asteroid> {
+	let mut rng = WorldRng::new(seed);
+	let count = (4.0 * extent.0 * extent.1 * DENSITY) as usize;
+	(0..count).map(|_| {
+		let origin = (rng.gen_range(-extent.0, extent.0), rng.gen_range(-extent.1, extent.1));
+		let radius = rng.gen_range(MIN_RADIUS, MAX_RADIUS);
+		let spin = rng.gen_range(-30.0, 30.0);
+		let vel = if rng.gen_bool(DRIFTING) {
+			let angle = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
+			let speed = rng.gen_range(0.0, MAX_DRIFT);
+			(angle.cos() * speed, angle.sin() * speed)
+		} else {
+			(0.0, 0.0)
+		};
+		Asteroid::new(origin, vel, spin, radius)
+	}).collect()
+}
@@ -0,0 +1,85 @@
+use serde_derive::*;
+
+use crate::reng::types::*;
+use crate::client::types::Instance2D;
+use super::zone::ring;
+use super::team::Team;
+
+//A circle teams fight over. A team takes it once its ships alone have held it long enough.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct CapturePoint {
+	pub pos          : (f32, f32),
+	pub radius       : f32,
+	//Seconds a team needs alone on the point to take it.
+	pub capture_time : f32,
+	pub owner        : Option<usize>,
+	//The team taking the point, and for how many seconds it has held it so far.
+	pub capturing    : Option<usize>,
+	pub progress     : f32,
+}
+
+impl CapturePoint {
+	pub fn new(pos : (f32, f32), radius : f32) -> Self {
+		Self {
+			pos,
+			radius,
+			capture_time : 5.0,
+			owner : None,
+			capturing : None,
+			progress : 0.0,
+		}
+	}
+
+	pub fn restart(&mut self) {
+		self.owner = None;
+		self.capturing = None;
+		self.progress = 0.0;
+	}
+
+	pub fn contains(&self, pos : (f32, f32)) -> bool {
+		let (dx, dy) = (pos.0 - self.pos.0, pos.1 - self.pos.1);
+		dx * dx + dy * dy <= self.radius * self.radius
+	}
+
+	//`present` holds each team with a living ship on the point once. Contested points hold still,
+	//empty ones slowly lose any progress. Returns the team that just took the point.
+	pub fn update(&mut self, timestep : f32, present : &[usize]) -> Option<usize> {
+		let team = match present {
+			[] => {
+				self.progress = (self.progress - timestep).max(0.0);
+				return None;
+			},
+			[team] => *team,
+			_ => return None,
+		};
+		if self.owner == Some(team) {
+			self.capturing = None;
+			self.progress = 0.0;
+			return None;
+		}
+		if self.capturing != Some(team) {
+			self.capturing = Some(team);
+			self.progress = 0.0;
+		}
+		self.progress += timestep;
+		if self.progress < self.capture_time {
+			return None;
+		}
+		self.owner = Some(team);
+		self.capturing = None;
+		self.progress = 0.0;
+		Some(team)
+	}
+
+	//The point in its owner's colour, with an inner ring growing as another team takes it.
+	pub fn render_to(&self, output_buffer : &mut Vec<Instance2D>, text_coords : GLvec4, teams : &[Team]) {
+		let tint = |team : Option<usize>, alpha : f32| {
+			let (r, g, b, _) = team.and_then(|team| teams.get(team)).map_or((1.0, 1.0, 1.0, 1.0), |team| team.tint);
+			GLvec4(r, g, b, alpha)
+		};
+		ring(output_buffer, text_coords, self.pos, self.radius, tint(self.owner, 0.8));
+		if self.capturing.is_some() && self.progress > 0.0 {
+			ring(output_buffer, text_coords, self.pos, self.radius * self.progress / self.capture_time, tint(self.capturing, 0.5));
+		}
+	}
+}
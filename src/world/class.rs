@@ -0,0 +1,110 @@
+use serde_derive::*;
+
+use crate::client::state::ClientTexture;
+use super::Sprite;
+use super::collision::Shape;
+
+pub const CLASSES_DIR : &str = "assets/ships";
+
+//Where a gun sits on the hull, relative to the ship's centre and facing along its x axis.
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Debug)]
+pub struct WeaponSlot {
+	pub offset : (f32, f32),
+	//Degrees off the ship's heading this gun fires at.
+	#[serde(default)]
+	pub angle  : f32,
+}
+
+//Everything that sets one kind of ship apart from another. The rest is shared through `ShipParams`.
+#[derive(Serialize, Clone, Deserialize, PartialEq, Debug)]
+pub struct ShipClass {
+	pub name         : String,
+	pub sprite       : ClientTexture,
+	#[serde(default = "white")]
+	pub tint         : (f32, f32, f32, f32),
+	//Radius of the hull, which the sprite and collision box are scaled to.
+	pub size         : f32,
+	//Degrees per second at full turn.
+	pub turn_rate    : f32,
+	pub thrust       : f32,
+	pub mass         : f32,
+	pub hull         : f32,
+	pub shield       : f32,
+	pub weapon_slots : Vec<WeaponSlot>,
+}
+
+fn white() -> (f32, f32, f32, f32) {
+	(1.0, 1.0, 1.0, 1.0)
+}
+
+impl Default for ShipClass {
+	fn default() -> Self {
+		Self {
+			name : String::from("fighter"),
+			sprite : ClientTexture::ShipSheet,
+			tint : white(),
+			size : 0.4,
+			turn_rate : 250.0,
+			thrust : 2.0,
+			mass : 1.0,
+			hull : 100.0,
+			shield : 50.0,
+			weapon_slots : vec![WeaponSlot { offset : (0.4, 0.0), angle : 0.0 }],
+		}
+	}
+}
+
+impl ShipClass {
+	pub fn half_extents(&self) -> (f32, f32) {
+		(self.size * 0.75, self.size)
+	}
+
+	pub fn shape(&self) -> Shape {
+		Shape::OrientedBox { half_extents : self.half_extents() }
+	}
+
+	pub fn sprite(&self) -> Sprite {
+		Sprite {
+			texture : self.sprite,
+			tint : self.tint,
+			scale : (self.size * 1.25, self.size * 1.25),
+			rotation : -90.0,
+		}
+	}
+
+	fn validate(&self) -> Result<(), String> {
+		if self.name.is_empty() {
+			return Err(String::from("a ship class has no name"));
+		}
+		if self.size <= 0.0 || self.mass <= 0.0 || self.hull <= 0.0 {
+			return Err(format!("ship class '{}' needs a positive size, mass and hull", self.name));
+		}
+		Ok(())
+	}
+}
+
+//Reads every `.json` class in `dir`, sorted by file name so the first one is the same default everywhere.
+pub fn load_classes(dir : impl AsRef<std::path::Path>) -> Result<Vec<ShipClass>, String> {
+	let dir = dir.as_ref();
+	let mut paths = std::fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+		.collect::<Vec<_>>();
+	paths.sort();
+
+	let mut classes : Vec<ShipClass> = vec![];
+	for path in paths {
+		let class : ShipClass = std::fs::read_to_string(&path).map_err(|err| err.to_string())
+			.and_then(|json| serde_json::from_str(&json).map_err(|err| err.to_string()))
+			.map_err(|err| format!("{}: {}", path.display(), err))?;
+		class.validate().map_err(|err| format!("{}: {}", path.display(), err))?;
+		if classes.iter().any(|other| other.name == class.name) {
+			return Err(format!("{}: ship class '{}' is defined twice", path.display(), class.name));
+		}
+		classes.push(class);
+	}
+	if classes.is_empty() {
+		return Err(format!("{}: no ship classes found", dir.display()));
+	}
+	Ok(classes)
+}
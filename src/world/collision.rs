@@ -0,0 +1,500 @@
+use serde_derive::*;
+use fnv::{FnvHashMap, FnvHashSet};
+
+use super::entity::EntityId;
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug)]
+pub enum Shape {
+	Circle { radius : f32 },
+	//Half extents along the body's local axes, rotated by the body's angle.
+	OrientedBox { half_extents : (f32, f32) },
+}
+
+impl Shape {
+	pub fn bounding_radius(&self) -> f32 {
+		match *self {
+			Shape::Circle { radius } => radius,
+			Shape::OrientedBox { half_extents : (x, y) } => (x * x + y * y).sqrt(),
+		}
+	}
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub enum BodyKey {
+	Ship(EntityId),
+	Projectile(EntityId),
+	//Asteroids aren't entities, so they are keyed by their index in the field.
+	Asteroid(usize),
+	Pickup(EntityId),
+	Pilot(EntityId),
+}
+
+#[derive(Clone, Debug)]
+pub struct Body {
+	pub key         : BodyKey,
+	pub shape       : Shape,
+	pub pos         : (f32, f32),
+	pub motion      : (f32, f32),
+	pub vel         : (f32, f32),
+	pub angle       : f32,
+	pub inv_mass    : f32,
+	pub restitution : f32,
+	pub sensor      : bool,
+}
+
+impl Body {
+	//`motion` is how far the body moved this step, so the sweep can start from `pos - motion`.
+	pub fn new(key : BodyKey, shape : Shape, start : (f32, f32), pos : (f32, f32), vel : (f32, f32), angle : f32) -> Self {
+		Self {
+			key,
+			shape,
+			pos,
+			motion : sub(pos, start),
+			vel,
+			angle,
+			inv_mass : 1.0,
+			restitution : 0.5,
+			sensor : false,
+		}
+	}
+
+	pub fn sensor(mut self) -> Self {
+		self.sensor = true;
+		self.inv_mass = 0.0;
+		self
+	}
+
+	//Fixed bodies push others out of their way without being moved themselves.
+	pub fn fixed(mut self) -> Self {
+		self.inv_mass = 0.0;
+		self
+	}
+
+	pub fn with_mass(mut self, mass : f32) -> Self {
+		self.inv_mass = 1.0 / mass;
+		self
+	}
+
+	fn start(&self) -> (f32, f32) {
+		sub(self.pos, self.motion)
+	}
+
+	fn pos_at(&self, t : f32) -> (f32, f32) {
+		add(self.start(), scale(self.motion, t))
+	}
+
+	fn swept_bounds(&self) -> ((f32, f32), (f32, f32)) {
+		let radius = self.shape.bounding_radius();
+		let start = self.start();
+		(
+			(start.0.min(self.pos.0) - radius, start.1.min(self.pos.1) - radius),
+			(start.0.max(self.pos.0) + radius, start.1.max(self.pos.1) + radius),
+		)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+	//Points from the first body towards the second.
+	pub normal : (f32, f32),
+	pub depth  : f32,
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug)]
+pub struct CollisionEvent {
+	pub a      : BodyKey,
+	pub b      : BodyKey,
+	pub normal : (f32, f32),
+	pub depth  : f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+	cell_size : f32,
+	cells     : FnvHashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+	pub fn new(cell_size : f32) -> Self {
+		Self {
+			cell_size,
+			cells : FnvHashMap::default(),
+		}
+	}
+
+	fn cell(&self, point : (f32, f32)) -> (i32, i32) {
+		((point.0 / self.cell_size).floor() as i32, (point.1 / self.cell_size).floor() as i32)
+	}
+
+	pub fn insert(&mut self, index : usize, bounds : ((f32, f32), (f32, f32))) {
+		let (min, max) = (self.cell(bounds.0), self.cell(bounds.1));
+		for x in min.0..=max.0 {
+			for y in min.1..=max.1 {
+				self.cells.entry((x, y)).or_insert_with(Vec::new).push(index);
+			}
+		}
+	}
+
+	pub fn pairs(&self) -> Vec<(usize, usize)> {
+		let mut seen = FnvHashSet::default();
+		let mut pairs = vec![];
+		for bucket in self.cells.values() {
+			for (n, &i) in bucket.iter().enumerate() {
+				for &j in &bucket[n + 1..] {
+					let pair = (i.min(j), i.max(j));
+					if seen.insert(pair) {
+						pairs.push(pair);
+					}
+				}
+			}
+		}
+		pairs.sort_unstable();
+		pairs
+	}
+}
+
+//Moves every body out of its contacts, applies impulses between solid bodies and reports every touching pair.
+pub fn step(bodies : &mut [Body], cell_size : f32, filter : impl Fn(&Body, &Body) -> bool) -> Vec<CollisionEvent> {
+	let mut grid = SpatialHash::new(cell_size);
+	for (index, body) in bodies.iter().enumerate() {
+		grid.insert(index, body.swept_bounds());
+	}
+
+	let mut events = vec![];
+	for (i, j) in grid.pairs() {
+		if (bodies[i].sensor && bodies[j].sensor) || !filter(&bodies[i], &bodies[j]) {
+			continue;
+		}
+
+		if let Some((toi, contact)) = sweep(&bodies[i], &bodies[j]) {
+			events.push(CollisionEvent {
+				a : bodies[i].key,
+				b : bodies[j].key,
+				normal : contact.normal,
+				depth : contact.depth,
+			});
+
+			if !bodies[i].sensor && !bodies[j].sensor {
+				//Bodies that would have passed through each other are stopped where they first touched.
+				if toi < 1.0 {
+					for &index in &[i, j] {
+						let body = &mut bodies[index];
+						body.pos = body.pos_at(toi);
+						body.motion = scale(body.motion, toi);
+					}
+				}
+				resolve(bodies, i, j, &contact);
+			}
+		}
+	}
+	events
+}
+
+//Finds the earliest time in the step, as a fraction of it, at which the two bodies touch.
+//Bodies keep their angle through a step, so each pair of shapes can be swept exactly rather than sampled.
+fn sweep(a : &Body, b : &Body) -> Option<(f32, Contact)> {
+	use Shape::*;
+	let start = sub(b.start(), a.start());
+	let motion = sub(b.motion, a.motion);
+	let (toi, normal) = match (a.shape, b.shape) {
+		(Circle { radius : ra }, Circle { radius : rb }) => sweep_circles(start, motion, ra + rb)?,
+		(OrientedBox { half_extents }, Circle { radius }) => sweep_circle_box(start, motion, radius, a.angle, half_extents)?,
+		(Circle { radius }, OrientedBox { half_extents }) => {
+			let (toi, normal) = sweep_circle_box(scale(start, -1.0), scale(motion, -1.0), radius, b.angle, half_extents)?;
+			(toi, scale(normal, -1.0))
+		},
+		(OrientedBox { half_extents : ha }, OrientedBox { half_extents : hb }) => sweep_boxes(start, motion, a.angle, ha, b.angle, hb)?,
+	};
+	//Bodies that only just touch at that time still collide, along the normal they met on.
+	let found = contact(a.shape, a.pos_at(toi), a.angle, b.shape, b.pos_at(toi), b.angle).unwrap_or(Contact { normal, depth : 0.0 });
+	Some((toi, found))
+}
+
+//Sweeps take the second body's start and motion relative to the first, and return when they meet and the normal they meet along.
+fn sweep_circles(start : (f32, f32), motion : (f32, f32), reach : f32) -> Option<(f32, (f32, f32))> {
+	let toi = first_touch(start, motion, reach, 0.0)?;
+	Some((toi, normalize(add(start, scale(motion, toi)))))
+}
+
+//The circle touches the box when its centre enters the box grown by the radius, which has rounded corners.
+fn sweep_circle_box(start : (f32, f32), motion : (f32, f32), radius : f32, box_angle : f32, half_extents : (f32, f32)) -> Option<(f32, (f32, f32))> {
+	let start = rotate(start, -box_angle);
+	let motion = rotate(motion, -box_angle);
+	let (toi, axis) = slabs(start, motion, &[
+		((1.0, 0.0), half_extents.0 + radius),
+		((0.0, 1.0), half_extents.1 + radius),
+	])?;
+	let pos = add(start, scale(motion, toi));
+	if pos.0.abs() <= half_extents.0 || pos.1.abs() <= half_extents.1 {
+		return Some((toi, rotate(axis, box_angle)));
+	}
+	//Entering the grown box by a corner only counts once the centre is within the radius of that corner.
+	let corner = (half_extents.0.copysign(pos.0), half_extents.1.copysign(pos.1));
+	let toi = first_touch(sub(start, corner), motion, radius, toi)?;
+	Some((toi, rotate(normalize(sub(add(start, scale(motion, toi)), corner)), box_angle)))
+}
+
+//Two boxes touch once they overlap along every separating axis at the same time.
+fn sweep_boxes(start : (f32, f32), motion : (f32, f32), a_angle : f32, ha : (f32, f32), b_angle : f32, hb : (f32, f32)) -> Option<(f32, (f32, f32))> {
+	let axes_a = axes(a_angle);
+	let axes_b = axes(b_angle);
+	let mut limits = [((0.0, 0.0), 0.0); 4];
+	for (limit, &axis) in limits.iter_mut().zip(&[axes_a.0, axes_a.1, axes_b.0, axes_b.1]) {
+		let reach_a = ha.0 * dot(axes_a.0, axis).abs() + ha.1 * dot(axes_a.1, axis).abs();
+		let reach_b = hb.0 * dot(axes_b.0, axis).abs() + hb.1 * dot(axes_b.1, axis).abs();
+		*limit = (axis, reach_a + reach_b);
+	}
+	slabs(start, motion, &limits)
+}
+
+//When a point moving from `start` by `motion` is first within `reach` of the origin along every axis, if it is during the step.
+//Also returns the axis it crossed last, facing the point, which is the normal for that time.
+fn slabs(start : (f32, f32), motion : (f32, f32), limits : &[((f32, f32), f32)]) -> Option<(f32, (f32, f32))> {
+	let (mut enter, mut exit, mut normal) = (0.0, 1.0, (0.0, 0.0));
+	for &(axis, reach) in limits {
+		let from = dot(start, axis);
+		let speed = dot(motion, axis);
+		if speed == 0.0 {
+			if from.abs() >= reach {
+				return None;
+			}
+			continue;
+		}
+		let (near, far) = ((-reach.copysign(speed) - from) / speed, (reach.copysign(speed) - from) / speed);
+		if near > enter {
+			enter = near;
+			normal = scale(axis, -speed.signum());
+		}
+		exit = far.min(exit);
+		if enter >= exit {
+			return None;
+		}
+	}
+	Some((enter, normal))
+}
+
+//The first time from `after` onward that a point moving from `start` by `motion` comes within `reach` of the origin.
+fn first_touch(start : (f32, f32), motion : (f32, f32), reach : f32, after : f32) -> Option<f32> {
+	let offset = add(start, scale(motion, after));
+	if dot(offset, offset) <= reach * reach {
+		return Some(after);
+	}
+	let qa = dot(motion, motion);
+	let qb = 2.0 * dot(start, motion);
+	let qc = dot(start, start) - reach * reach;
+	let discriminant = qb * qb - 4.0 * qa * qc;
+	if qa <= 0.0 || discriminant < 0.0 {
+		return None;
+	}
+	let t = (-qb - discriminant.sqrt()) / (2.0 * qa);
+	if t < after || t > 1.0 {
+		return None;
+	}
+	Some(t)
+}
+
+fn resolve(bodies : &mut [Body], i : usize, j : usize, contact : &Contact) {
+	const CORRECTION : f32 = 0.8;
+	const SLOP       : f32 = 0.005;
+
+	let inv_mass = bodies[i].inv_mass + bodies[j].inv_mass;
+	if inv_mass <= 0.0 {
+		return;
+	}
+
+	let normal = contact.normal;
+	let closing = dot(sub(bodies[j].vel, bodies[i].vel), normal);
+	if closing < 0.0 {
+		let restitution = bodies[i].restitution.min(bodies[j].restitution);
+		let impulse = -(1.0 + restitution) * closing / inv_mass;
+		bodies[i].vel = sub(bodies[i].vel, scale(normal, impulse * bodies[i].inv_mass));
+		bodies[j].vel = add(bodies[j].vel, scale(normal, impulse * bodies[j].inv_mass));
+	}
+
+	let correction = scale(normal, (contact.depth - SLOP).max(0.0) / inv_mass * CORRECTION);
+	bodies[i].pos = sub(bodies[i].pos, scale(correction, bodies[i].inv_mass));
+	bodies[j].pos = add(bodies[j].pos, scale(correction, bodies[j].inv_mass));
+}
+
+pub fn contact(a : Shape, a_pos : (f32, f32), a_angle : f32, b : Shape, b_pos : (f32, f32), b_angle : f32) -> Option<Contact> {
+	use Shape::*;
+	match (a, b) {
+		(Circle { radius : ra }, Circle { radius : rb }) => {
+			let offset = sub(b_pos, a_pos);
+			let distance = length(offset);
+			if distance >= ra + rb {
+				return None;
+			}
+			let normal = if distance > 0.0 { scale(offset, 1.0 / distance) } else { (1.0, 0.0) };
+			Some(Contact {
+				normal,
+				depth : ra + rb - distance,
+			})
+		},
+		(OrientedBox { half_extents }, Circle { radius }) => {
+			circle_box(b_pos, radius, a_pos, a_angle, half_extents)
+		},
+		(Circle { radius }, OrientedBox { half_extents }) => {
+			circle_box(a_pos, radius, b_pos, b_angle, half_extents).map(|found| Contact {
+				normal : scale(found.normal, -1.0),
+				..found
+			})
+		},
+		(OrientedBox { half_extents : ha }, OrientedBox { half_extents : hb }) => {
+			let offset = sub(b_pos, a_pos);
+			let axes_a = axes(a_angle);
+			let axes_b = axes(b_angle);
+
+			let mut best : Option<Contact> = None;
+			for &axis in &[axes_a.0, axes_a.1, axes_b.0, axes_b.1] {
+				let reach_a = ha.0 * dot(axes_a.0, axis).abs() + ha.1 * dot(axes_a.1, axis).abs();
+				let reach_b = hb.0 * dot(axes_b.0, axis).abs() + hb.1 * dot(axes_b.1, axis).abs();
+				let separation = dot(offset, axis);
+				let depth = reach_a + reach_b - separation.abs();
+				if depth <= 0.0 {
+					return None;
+				}
+				if best.map_or(true, |best| depth < best.depth) {
+					let normal = if separation < 0.0 { scale(axis, -1.0) } else { axis };
+					best = Some(Contact { normal, depth });
+				}
+			}
+			best
+		},
+	}
+}
+
+//Contact normal points from the box towards the circle.
+fn circle_box(center : (f32, f32), radius : f32, box_pos : (f32, f32), box_angle : f32, half_extents : (f32, f32)) -> Option<Contact> {
+	let local = rotate(sub(center, box_pos), -box_angle);
+	let clamped = (
+		local.0.max(-half_extents.0).min(half_extents.0),
+		local.1.max(-half_extents.1).min(half_extents.1),
+	);
+
+	let (normal, depth) = if clamped == local {
+		let gap_x = half_extents.0 - local.0.abs();
+		let gap_y = half_extents.1 - local.1.abs();
+		if gap_x < gap_y {
+			((if local.0 < 0.0 { -1.0 } else { 1.0 }, 0.0), radius + gap_x)
+		} else {
+			((0.0, if local.1 < 0.0 { -1.0 } else { 1.0 }), radius + gap_y)
+		}
+	} else {
+		let offset = sub(local, clamped);
+		let distance = length(offset);
+		if distance >= radius {
+			return None;
+		}
+		(scale(offset, 1.0 / distance), radius - distance)
+	};
+
+	Some(Contact {
+		normal : rotate(normal, box_angle),
+		depth,
+	})
+}
+
+fn axes(angle : f32) -> ((f32, f32), (f32, f32)) {
+	let (sin, cos) = angle.to_radians().sin_cos();
+	((cos, sin), (-sin, cos))
+}
+
+fn rotate(v : (f32, f32), angle : f32) -> (f32, f32) {
+	let (sin, cos) = angle.to_radians().sin_cos();
+	(v.0 * cos - v.1 * sin, v.0 * sin + v.1 * cos)
+}
+
+fn add(a : (f32, f32), b : (f32, f32)) -> (f32, f32) {
+	(a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a : (f32, f32), b : (f32, f32)) -> (f32, f32) {
+	(a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a : (f32, f32), s : f32) -> (f32, f32) {
+	(a.0 * s, a.1 * s)
+}
+
+fn dot(a : (f32, f32), b : (f32, f32)) -> f32 {
+	a.0 * b.0 + a.1 * b.1
+}
+
+fn length(a : (f32, f32)) -> f32 {
+	dot(a, a).sqrt()
+}
+
+fn normalize(a : (f32, f32)) -> (f32, f32) {
+	let len = length(a);
+	if len > 0.0 { scale(a, 1.0 / len) } else { (0.0, 0.0) }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(index : u32) -> BodyKey {
+		BodyKey::Ship(EntityId { index, generation : 0 })
+	}
+
+	//A wall far thinner than anything sent at it, standing across the x axis at the origin.
+	fn wall() -> Body {
+		Body::new(key(0), Shape::OrientedBox { half_extents : (0.01, 1.0) }, (0.0, 0.0), (0.0, 0.0), (0.0, 0.0), 0.0).fixed()
+	}
+
+	fn moving(index : u32, shape : Shape, start : (f32, f32), pos : (f32, f32)) -> Body {
+		Body::new(key(index), shape, start, pos, scale(sub(pos, start), 60.0), 0.0)
+	}
+
+	#[test]
+	fn fast_circle_stops_at_thin_wall() {
+		let mut bodies = [moving(1, Shape::Circle { radius : 0.02 }, (-5.0, 0.0), (5.0, 0.0)), wall()];
+		let events = step(&mut bodies, 1.0, |_, _| true);
+		assert_eq!(events.len(), 1);
+		assert!((bodies[0].pos.0 + 0.03).abs() < 1e-3, "stopped at {:?}", bodies[0].pos);
+		assert!(bodies[0].vel.0 < 0.0);
+	}
+
+	#[test]
+	fn fast_box_stops_at_thin_wall() {
+		let mut bodies = [moving(1, Shape::OrientedBox { half_extents : (0.05, 0.05) }, (-5.0, 0.3), (5.0, 0.3)), wall()];
+		let events = step(&mut bodies, 1.0, |_, _| true);
+		assert_eq!(events.len(), 1);
+		assert!((bodies[0].pos.0 + 0.06).abs() < 1e-3, "stopped at {:?}", bodies[0].pos);
+		assert_eq!(bodies[0].pos.1, 0.3);
+	}
+
+	//Each circle covers its own size forty times over, and the two only meet around the middle of the step.
+	#[test]
+	fn crossing_circles_meet_midway() {
+		let shape = Shape::Circle { radius : 0.05 };
+		let mut bodies = [moving(1, shape, (-2.0, 0.0), (2.0, 0.0)), moving(2, shape, (0.0, -2.0), (0.0, 2.0))];
+		let events = step(&mut bodies, 1.0, |_, _| true);
+		assert_eq!(events.len(), 1);
+		assert!(bodies[0].pos.0.abs() < 0.1 && bodies[1].pos.1.abs() < 0.1, "met at {:?} and {:?}", bodies[0].pos, bodies[1].pos);
+		assert!(length(sub(bodies[1].pos, bodies[0].pos)) >= 0.095);
+	}
+
+	#[test]
+	fn crossing_circle_and_box_meet_midway() {
+		let mut bodies = [
+			moving(1, Shape::OrientedBox { half_extents : (0.2, 0.05) }, (0.0, -3.0), (0.0, 3.0)),
+			moving(2, Shape::Circle { radius : 0.02 }, (-3.0, 0.0), (3.0, 0.0)),
+		];
+		let events = step(&mut bodies, 1.0, |_, _| true);
+		assert_eq!(events.len(), 1);
+		assert!(bodies[0].pos.1.abs() < 0.2 && bodies[1].pos.0.abs() < 0.3, "met at {:?} and {:?}", bodies[0].pos, bodies[1].pos);
+		let overlap = contact(bodies[0].shape, bodies[0].pos, 0.0, bodies[1].shape, bodies[1].pos, 0.0).map(|found| found.depth);
+		assert!(overlap.unwrap_or(0.0) < 0.01);
+	}
+
+	//The path crosses the box grown by the radius, but only through a corner the circle itself never reaches.
+	#[test]
+	fn circle_passing_a_corner_misses() {
+		let block = Body::new(key(0), Shape::OrientedBox { half_extents : (1.0, 1.0) }, (0.0, 0.0), (0.0, 0.0), (0.0, 0.0), 0.0).fixed();
+		let mut bodies = [moving(1, Shape::Circle { radius : 0.6 }, (0.0, 3.0), (3.0, 0.0)), block];
+		assert!(step(&mut bodies, 1.0, |_, _| true).is_empty());
+		assert_eq!(bodies[0].pos, (3.0, 0.0));
+	}
+}
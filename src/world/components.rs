@@ -0,0 +1,42 @@
+use serde_derive::*;
+
+use crate::client::state::ClientTexture;
+use super::entity::EntityId;
+
+#[derive(Serialize, Clone, Copy, Deserialize, Default, Debug)]
+pub struct Transform {
+	pub pos   : (f32, f32),
+	//Facing in degrees, anticlockwise from the x axis.
+	pub angle : f32,
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, Default, Debug)]
+pub struct Velocity {
+	pub vel  : (f32, f32),
+	//Degrees per second.
+	pub spin : f32,
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug)]
+pub struct Health {
+	pub hull : f32,
+}
+
+impl Health {
+	pub fn alive(&self) -> bool {
+		self.hull > 0.0
+	}
+}
+
+//Who is responsible for an entity, like the ship that fired a projectile.
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Debug)]
+pub struct Owner(pub EntityId);
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug)]
+pub struct Sprite {
+	pub texture  : ClientTexture,
+	pub tint     : (f32, f32, f32, f32),
+	pub scale    : (f32, f32),
+	//Added to the transform's angle, for textures that don't face along the x axis.
+	pub rotation : f32,
+}
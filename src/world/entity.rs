@@ -0,0 +1,130 @@
+use serde_derive::*;
+
+//An index into the entity store, with a generation so ids of despawned entities never alias their replacements.
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct EntityId {
+	pub index      : u32,
+	pub generation : u32,
+}
+
+impl std::fmt::Display for EntityId {
+	fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}v{}", self.index, self.generation)
+	}
+}
+
+//Reads back the `3v0` form ids are displayed in.
+impl std::str::FromStr for EntityId {
+	type Err = String;
+
+	fn from_str(id : &str) -> Result<Self, Self::Err> {
+		let mut parts = id.splitn(2, 'v').map(str::parse::<u32>);
+		match (parts.next(), parts.next()) {
+			(Some(Ok(index)), Some(Ok(generation))) => Ok(Self { index, generation }),
+			_ => Err(format!("'{}' isn't an entity id like 3v0", id)),
+		}
+	}
+}
+
+#[derive(Serialize, Clone, Deserialize, Default, Debug)]
+pub struct Entities {
+	generations : Vec<u32>,
+	alive       : Vec<bool>,
+	free        : Vec<u32>,
+}
+
+impl Entities {
+	pub fn spawn(&mut self) -> EntityId {
+		match self.free.pop() {
+			Some(index) => {
+				self.alive[index as usize] = true;
+				EntityId { index, generation : self.generations[index as usize] }
+			},
+			None => {
+				self.generations.push(0);
+				self.alive.push(true);
+				EntityId { index : self.generations.len() as u32 - 1, generation : 0 }
+			},
+		}
+	}
+
+	//Returns whether the entity was alive, bumping the generation so the old id stops matching.
+	pub fn despawn(&mut self, id : EntityId) -> bool {
+		if !self.contains(id) {
+			return false;
+		}
+		let index = id.index as usize;
+		self.alive[index] = false;
+		self.generations[index] += 1;
+		self.free.push(id.index);
+		true
+	}
+
+	pub fn contains(&self, id : EntityId) -> bool {
+		let index = id.index as usize;
+		index < self.alive.len() && self.alive[index] && self.generations[index] == id.generation
+	}
+}
+
+//One component per entity, stored by index and tagged with the generation it was inserted for.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Storage<T> {
+	slots : Vec<Option<(u32, T)>>,
+}
+
+impl<T> Default for Storage<T> {
+	fn default() -> Self {
+		Self { slots : vec![] }
+	}
+}
+
+impl<T> Storage<T> {
+	pub fn insert(&mut self, id : EntityId, component : T) {
+		let index = id.index as usize;
+		if self.slots.len() <= index {
+			self.slots.resize_with(index + 1, || None);
+		}
+		self.slots[index] = Some((id.generation, component));
+	}
+
+	pub fn remove(&mut self, id : EntityId) -> Option<T> {
+		if !self.contains(id) {
+			return None;
+		}
+		self.slots[id.index as usize].take().map(|(_, component)| component)
+	}
+
+	pub fn contains(&self, id : EntityId) -> bool {
+		self.get(id).is_some()
+	}
+
+	pub fn get(&self, id : EntityId) -> Option<&T> {
+		match self.slots.get(id.index as usize) {
+			Some(Some((generation, component))) if *generation == id.generation => Some(component),
+			_ => None,
+		}
+	}
+
+	pub fn get_mut(&mut self, id : EntityId) -> Option<&mut T> {
+		match self.slots.get_mut(id.index as usize) {
+			Some(Some((generation, component))) if *generation == id.generation => Some(component),
+			_ => None,
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+		self.slots.iter().enumerate().filter_map(|(index, slot)| {
+			slot.as_ref().map(|(generation, component)| (EntityId { index : index as u32, generation : *generation }, component))
+		})
+	}
+
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+		self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+			slot.as_mut().map(|(generation, component)| (EntityId { index : index as u32, generation : *generation }, component))
+		})
+	}
+
+	pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+		self.iter().map(|(id, _)| id)
+	}
+}
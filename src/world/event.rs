@@ -0,0 +1,55 @@
+use serde_derive::*;
+
+use super::entity::EntityId;
+use super::inventory::Resource;
+
+//Everything worth reacting to that happens in the world, so nobody has to diff snapshots to find out.
+#[derive(Serialize, Clone, Deserialize, PartialEq, Debug)]
+pub enum WorldEvent {
+	Turned { ship : EntityId, direction : i8 },
+	Thrusting { ship : EntityId, on : bool },
+	Braking { ship : EntityId, on : bool },
+	Strafing { ship : EntityId, direction : i8 },
+	Trigger { ship : EntityId, held : bool },
+	Shield { ship : EntityId, raised : bool },
+	Fired { ship : EntityId, projectile : EntityId },
+	//Pilots shot on foot are hit with their own id in `ship`.
+	Hit { ship : EntityId, by : Option<EntityId>, damage : f32 },
+	AsteroidDestroyed { pos : (f32, f32), radius : f32 },
+	PickedUp { ship : EntityId, kind : Resource, amount : f32 },
+	Died { ship : EntityId, killer : Option<EntityId> },
+	Respawned { ship : EntityId },
+	ClassChanged { ship : EntityId, class : String },
+	Walking { pilot : EntityId, direction : (i8, i8) },
+	Boarded { pilot : EntityId, ship : EntityId },
+	Left { pilot : EntityId, ship : EntityId },
+	Captured { team : usize },
+}
+
+#[derive(Serialize, Clone, Deserialize, PartialEq, Debug)]
+pub struct TickEvent {
+	//The substep the event happened on, counted from the start of the match.
+	//Hits and pickups the server resolves after an update are stamped with its last substep.
+	pub tick  : u64,
+	pub event : WorldEvent,
+}
+
+//Events wait here until whoever runs the world drains them, once per update.
+#[derive(Clone, Default, Debug)]
+pub struct EventQueue {
+	events : Vec<TickEvent>,
+}
+
+impl EventQueue {
+	pub fn push(&mut self, tick : u64, event : WorldEvent) {
+		self.events.push(TickEvent { tick, event });
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.events.is_empty()
+	}
+
+	pub fn drain(&mut self) -> Vec<TickEvent> {
+		std::mem::take(&mut self.events)
+	}
+}
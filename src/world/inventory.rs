@@ -0,0 +1,88 @@
+use serde_derive::*;
+use strum_macros::{IntoStaticStr, EnumIter};
+
+use super::ShipParams;
+
+#[derive(Serialize, Clone, Copy, Deserialize, PartialEq, Eq, Hash, IntoStaticStr, EnumIter, Debug)]
+pub enum Resource {
+	Fuel,
+	Ammo,
+	Scrap,
+	Oxygen,
+}
+
+impl Resource {
+	pub fn capacity(self, params : &ShipParams) -> f32 {
+		match self {
+			Resource::Fuel => params.fuel_capacity,
+			Resource::Ammo => params.ammo_capacity,
+			Resource::Scrap => params.scrap_capacity,
+			Resource::Oxygen => params.oxygen_capacity,
+		}
+	}
+}
+
+#[derive(Serialize, Clone, Deserialize, Default, Debug)]
+pub struct Inventory {
+	pub fuel   : f32,
+	pub ammo   : f32,
+	pub scrap  : f32,
+	pub oxygen : f32,
+}
+
+impl Inventory {
+	//What a ship carries when it (re)spawns.
+	pub fn loadout(params : &ShipParams) -> Self {
+		Self {
+			fuel : params.fuel_capacity,
+			ammo : params.ammo_capacity,
+			scrap : 0.0,
+			oxygen : params.oxygen_capacity,
+		}
+	}
+
+	pub fn get(&self, resource : Resource) -> f32 {
+		match resource {
+			Resource::Fuel => self.fuel,
+			Resource::Ammo => self.ammo,
+			Resource::Scrap => self.scrap,
+			Resource::Oxygen => self.oxygen,
+		}
+	}
+
+	fn get_mut(&mut self, resource : Resource) -> &mut f32 {
+		match resource {
+			Resource::Fuel => &mut self.fuel,
+			Resource::Ammo => &mut self.ammo,
+			Resource::Scrap => &mut self.scrap,
+			Resource::Oxygen => &mut self.oxygen,
+		}
+	}
+
+	//Adds as much as fits under the resource's capacity, returning how much was taken.
+	pub fn add(&mut self, resource : Resource, amount : f32, params : &ShipParams) -> f32 {
+		let capacity = resource.capacity(params);
+		let held = self.get_mut(resource);
+		let taken = amount.min(capacity - *held).max(0.0);
+		*held += taken;
+		taken
+	}
+
+	//Removes `amount` only if all of it is held, returning whether it was.
+	pub fn take(&mut self, resource : Resource, amount : f32) -> bool {
+		let held = self.get_mut(resource);
+		if *held < amount {
+			return false;
+		}
+		*held -= amount;
+		true
+	}
+
+	//Removes up to `amount`, returning whether there was any left to remove.
+	pub fn drain(&mut self, resource : Resource, amount : f32) -> bool {
+		let held = self.get_mut(resource);
+		let any = *held > 0.0;
+		*held = (*held - amount).max(0.0);
+		any
+	}
+}
@@ -0,0 +1,1243 @@
+mod projectile;
+mod arena;
+mod asteroid;
+mod pickup;
+mod inventory;
+mod entity;
+mod components;
+mod class;
+mod physics;
+mod rng;
+mod event;
+mod pilot;
+mod team;
+mod capture;
+pub mod zone;
+pub mod collision;
+
+use serde_derive::*;
+use fnv::FnvHashMap;
+use rand::{Rng, RngCore};
+
+use crate::comms;
+use crate::format;
+use crate::reng::types::*;
+use crate::client::types::Instance2D;
+use crate::client::state::ClientTexture;
+use crate::client::sheet::{Animation, SpriteSheet};
+
+pub use projectile::Projectile;
+pub use arena::{Arena, Edge};
+pub use asteroid::AsteroidField;
+pub use pickup::Pickup;
+pub use inventory::{Inventory, Resource};
+pub use zone::Zone;
+pub use entity::{EntityId, Entities, Storage};
+pub use components::{Transform, Velocity, Health, Owner, Sprite};
+pub use class::{ShipClass, WeaponSlot, load_classes, CLASSES_DIR};
+pub use physics::RigidBody;
+pub use rng::WorldRng;
+pub use event::{WorldEvent, TickEvent, EventQueue};
+pub use pilot::Pilot;
+pub use team::{Team, default_teams, MAX_TEAMS};
+pub use capture::CapturePoint;
+use collision::{Body, BodyKey};
+
+const COLLISION_CELL : f32 = 1.0;
+//Scattered pickups per square unit of the arena, topped up as they are collected.
+const PICKUP_DENSITY : f32 = 0.03;
+//Ships spawn inside this fraction of the arena, away from its edges.
+const SPAWN_MARGIN   : f32 = 0.8;
+//Ships under this fraction of their class's hull look damaged.
+const DAMAGED_HULL   : f32 = 0.3;
+//Ships this close to an asteroid's surface count as docked to it.
+const DOCK_GAP       : f32 = 0.15;
+
+//Ships, projectiles and pickups are entities made of components, so systems only touch the components they need.
+//Asteroids stay in their own field, since they are regenerated from its seed rather than sent.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct World {
+	pub entities    : Entities,
+	pub transforms  : Storage<Transform>,
+	pub velocities  : Storage<Velocity>,
+	pub bodies      : Storage<RigidBody>,
+	pub ships       : Storage<Ship>,
+	pub pilots      : Storage<Pilot>,
+	pub healths     : Storage<Health>,
+	pub owners      : Storage<Owner>,
+	pub sprites     : Storage<Sprite>,
+	pub projectiles : Storage<Projectile>,
+	pub pickups     : Storage<Pickup>,
+	pub ship_params : ShipParams,
+	//Sent along with the world, so clients draw and predict every class the server loaded.
+	pub classes     : Vec<ShipClass>,
+	pub arena       : Arena,
+	pub asteroids   : AsteroidField,
+	//Only battle royale matches have a safe zone.
+	pub zone        : Option<Zone>,
+	//Empty outside team modes, where every ship is its own side.
+	pub teams       : Vec<Team>,
+	//Only capture matches have a point to hold.
+	pub capture     : Option<CapturePoint>,
+	//Time left over from the last update, always less than a substep.
+	pub lag         : f32,
+	//Substeps run since the match began, which every event is stamped with.
+	pub tick        : u64,
+	//Every random gameplay decision draws from this, so snapshots and saves carry on with the same numbers.
+	pub rng         : WorldRng,
+	#[serde(skip)]
+	pub collisions  : Vec<collision::CollisionEvent>,
+	#[serde(skip)]
+	pub events      : EventQueue,
+}
+
+impl World {
+	pub fn new() -> Self {
+		let arena = Arena::default();
+		let mut rng = WorldRng::new(rand::random());
+		Self {
+			entities : Entities::default(),
+			transforms : Storage::default(),
+			velocities : Storage::default(),
+			bodies : Storage::default(),
+			ships : Storage::default(),
+			pilots : Storage::default(),
+			healths : Storage::default(),
+			owners : Storage::default(),
+			sprites : Storage::default(),
+			projectiles : Storage::default(),
+			pickups : Storage::default(),
+			ship_params : ShipParams::default(),
+			classes : vec![ShipClass::default()],
+			asteroids : AsteroidField::new(rng.next_u64(), arena.half_size),
+			zone : None,
+			teams : vec![],
+			capture : None,
+			lag : 0.0,
+			tick : 0,
+			rng,
+			arena,
+			collisions : vec![],
+			events : EventQueue::default(),
+		}
+	}
+
+	//Regenerates the asteroid field to fill the current arena, and restarts the world's random numbers from the same seed.
+	pub fn reseed(&mut self, seed : u64) {
+		self.asteroids = AsteroidField::new(seed, self.arena.half_size);
+		self.rng = WorldRng::new(seed);
+	}
+
+	pub fn save(&self, path : impl AsRef<std::path::Path>) -> Result<(), format::FormatError> {
+		format::write_world(self, path.as_ref())
+	}
+
+	pub fn load(path : impl AsRef<std::path::Path>) -> Result<Self, format::FormatError> {
+		format::read_world(path.as_ref())
+	}
+
+	pub fn despawn(&mut self, id : EntityId) {
+		if self.entities.despawn(id) {
+			self.transforms.remove(id);
+			self.velocities.remove(id);
+			self.bodies.remove(id);
+			self.ships.remove(id);
+			self.pilots.remove(id);
+			self.healths.remove(id);
+			self.owners.remove(id);
+			self.sprites.remove(id);
+			self.projectiles.remove(id);
+			self.pickups.remove(id);
+		}
+	}
+
+	pub fn alive(&self, id : EntityId) -> bool {
+		self.healths.get(id).map_or(false, Health::alive)
+	}
+
+	pub fn pos(&self, id : EntityId) -> Option<(f32, f32)> {
+		self.transforms.get(id).map(|transform| transform.pos)
+	}
+
+	//Falls back to the first class, so ships of a class that is no longer loaded still fly.
+	pub fn class(&self, name : &str) -> &ShipClass {
+		find_class(&self.classes, name)
+	}
+
+	pub fn class_of(&self, id : EntityId) -> &ShipClass {
+		self.class(self.ships.get(id).map_or("", |ship| ship.class.as_str()))
+	}
+
+	//The ship a pilot is flying, if it's aboard one.
+	pub fn vehicle(&self, pilot : EntityId) -> Option<EntityId> {
+		self.pilots.get(pilot).and_then(|pilot| pilot.ship)
+	}
+
+	//What a pilot's player sees and steers, its ship or the pilot itself when on foot.
+	pub fn controlled(&self, pilot : EntityId) -> EntityId {
+		self.vehicle(pilot).unwrap_or(pilot)
+	}
+
+	//The team of whoever is flying a ship, or of a pilot on foot. Ships nobody flies are on no side.
+	pub fn team_of(&self, id : EntityId) -> Option<usize> {
+		let pilot = match self.ships.get(id) {
+			Some(ship) => ship.pilot?,
+			None => id,
+		};
+		self.pilots.get(pilot)?.team
+	}
+
+	pub fn set_team(&mut self, pilot : EntityId, team : Option<usize>) {
+		if let Some(pilot) = self.pilots.get_mut(pilot) {
+			pilot.team = team;
+		}
+	}
+
+	//The colour of the team a ship's pilot, or a pilot on foot, is on.
+	fn team_tint(&self, id : EntityId) -> Option<(f32, f32, f32, f32)> {
+		self.teams.get(self.team_of(id)?).map(|team| team.tint)
+	}
+
+	//A new pilot, put straight aboard `ship`.
+	pub fn spawn_pilot(&mut self, ship : EntityId) -> EntityId {
+		let id = self.entities.spawn();
+		self.pilots.insert(id, Pilot::aboard(ship));
+		if let Some(ship) = self.ships.get_mut(ship) {
+			ship.pilot = Some(id);
+		}
+		id
+	}
+
+	//Takes a pilot out of the world, leaving its ship idle for someone else.
+	pub fn remove_pilot(&mut self, pilot : EntityId) {
+		if let Some(ship) = self.vehicle(pilot) {
+			if let Some(ship) = self.ships.get_mut(ship) {
+				ship.pilot = None;
+				ship.release_controls();
+			}
+		}
+		self.despawn(pilot);
+	}
+
+	//Nobody is playing the pilots of a loaded world, so their ships are freed up for whoever joins.
+	pub fn clear_pilots(&mut self) {
+		let pilots = self.pilots.ids().collect::<Vec<_>>();
+		for pilot in pilots {
+			self.remove_pilot(pilot);
+		}
+	}
+
+	//Pilots on foot can climb into any living ship nobody is flying, if they're close enough to its hull.
+	fn board(&mut self, pilot_id : EntityId) {
+		let pos = match self.pilots.get(pilot_id) {
+			Some(pilot) if pilot.ship.is_none() => self.pos(pilot_id),
+			_ => None,
+		};
+		let pos = match pos {
+			Some(pos) => pos,
+			None => return,
+		};
+		let nearest = self.ships.iter()
+			.filter(|(id, ship)| ship.pilot.is_none() && self.alive(*id))
+			.filter_map(|(id, ship)| Some((id, distance(self.pos(id)?, pos) - self.class(&ship.class).size)))
+			.filter(|(_, gap)| *gap < self.ship_params.board_range)
+			.min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+		if let Some((ship, _)) = nearest {
+			self.embark(pilot_id, ship);
+		}
+	}
+
+	fn embark(&mut self, pilot_id : EntityId, ship_id : EntityId) {
+		self.seat(pilot_id, ship_id);
+		self.events.push(self.tick, WorldEvent::Boarded { pilot : pilot_id, ship : ship_id });
+	}
+
+	//Puts a pilot aboard, dropping everything it only has while on foot.
+	fn seat(&mut self, pilot_id : EntityId, ship_id : EntityId) {
+		if let Some(ship) = self.ships.get_mut(ship_id) {
+			ship.pilot = Some(pilot_id);
+		}
+		if let Some(pilot) = self.pilots.get_mut(pilot_id) {
+			pilot.ship = Some(ship_id);
+			pilot.walking = (0, 0);
+		}
+		self.transforms.remove(pilot_id);
+		self.velocities.remove(pilot_id);
+		self.healths.remove(pilot_id);
+		self.sprites.remove(pilot_id);
+	}
+
+	//Pilots can only step out onto an asteroid their living ship is docked against and drifting along with.
+	fn leave(&mut self, pilot_id : EntityId) {
+		let ship_id = match self.vehicle(pilot_id) {
+			Some(ship) if self.alive(ship) => ship,
+			_ => return,
+		};
+		let (transform, velocity) = match (self.transforms.get(ship_id), self.velocities.get(ship_id)) {
+			(Some(transform), Some(velocity)) => (*transform, *velocity),
+			_ => return,
+		};
+		let ground = match self.surface(transform.pos, self.class_of(ship_id).size + DOCK_GAP) {
+			Some(index) => index,
+			None => return,
+		};
+		let (centre, asteroid) = (self.asteroids.pos(ground), self.asteroids.get(ground));
+		if distance(velocity.vel, asteroid.vel) > self.ship_params.leave_speed {
+			return;
+		}
+
+		//The hatch opens onto the nearest edge of the asteroid.
+		let (dx, dy) = (transform.pos.0 - centre.0, transform.pos.1 - centre.1);
+		let (gap, reach) = ((dx * dx + dy * dy).sqrt(), (asteroid.radius - Pilot::RADIUS).max(0.0));
+		let pos = if gap > 0.0 { (centre.0 + dx / gap * reach, centre.1 + dy / gap * reach) } else { centre };
+		let ground = asteroid.vel;
+
+		if let Some(ship) = self.ships.get_mut(ship_id) {
+			ship.pilot = None;
+			ship.release_controls();
+		}
+		if let Some(pilot) = self.pilots.get_mut(pilot_id) {
+			pilot.ship = None;
+			pilot.walking = (0, 0);
+		}
+		self.transforms.insert(pilot_id, Transform { pos, angle : transform.angle });
+		self.velocities.insert(pilot_id, Velocity { vel : ground, spin : 0.0 });
+		self.healths.insert(pilot_id, Health { hull : self.ship_params.pilot_health });
+		self.sprites.insert(pilot_id, Pilot::SPRITE);
+		self.events.push(self.tick, WorldEvent::Left { pilot : pilot_id, ship : ship_id });
+	}
+
+	fn walk(&mut self, pilot_id : EntityId, direction : (i8, i8)) {
+		let direction = (direction.0.signum(), direction.1.signum());
+		match self.pilots.get_mut(pilot_id) {
+			Some(pilot) if pilot.ship.is_none() && pilot.walking != direction => pilot.walking = direction,
+			_ => return,
+		}
+		self.events.push(self.tick, WorldEvent::Walking { pilot : pilot_id, direction });
+	}
+
+	//The asteroid whose surface is within `reach` of `pos`, if it isn't out in open space.
+	fn surface(&self, pos : (f32, f32), reach : f32) -> Option<usize> {
+		self.asteroids.iter()
+			.find(|(index, asteroid)| distance(self.asteroids.pos(*index), pos) < asteroid.radius + reach)
+			.map(|(index, _)| index)
+	}
+
+	//Actions come from a pilot, and steer its ship while it is aboard one.
+	pub fn process(&mut self, pilot_id : EntityId, action : &comms::Action) {
+		use comms::Action::*;
+		match action {
+			Board => self.board(pilot_id),
+			Leave => self.leave(pilot_id),
+			Walk(x, y) => self.walk(pilot_id, (*x, *y)),
+			_ => if let Some(ship) = self.vehicle(pilot_id) {
+				self.steer(ship, action);
+			},
+		}
+	}
+
+	fn steer(&mut self, ship_id : EntityId, action : &comms::Action) {
+		use comms::Action::*;
+		if !self.alive(ship_id) {
+			return;
+		}
+		let player_ship = match self.ships.get_mut(ship_id) {
+			Some(ship) => ship,
+			None => return,
+		};
+		let event = match action {
+			TurnShip(dir) if player_ship.turning != *dir => {
+				player_ship.turning = *dir;
+				WorldEvent::Turned { ship : ship_id, direction : *dir }
+			},
+			Thrust(on) if player_ship.thrusting != *on => {
+				player_ship.thrusting = *on;
+				WorldEvent::Thrusting { ship : ship_id, on : *on }
+			},
+			Brake(on) if player_ship.braking != *on => {
+				player_ship.braking = *on;
+				WorldEvent::Braking { ship : ship_id, on : *on }
+			},
+			Strafe(dir) if player_ship.strafing != *dir => {
+				player_ship.strafing = *dir;
+				WorldEvent::Strafing { ship : ship_id, direction : *dir }
+			},
+			Fire(on) if player_ship.firing != *on => {
+				player_ship.firing = *on;
+				WorldEvent::Trigger { ship : ship_id, held : *on }
+			},
+			RaiseShield(on) if player_ship.shielding != *on => {
+				player_ship.shielding = *on;
+				WorldEvent::Shield { ship : ship_id, raised : *on }
+			},
+			//Repeating a control the ship already has changes nothing, so there's nothing to report.
+			TurnShip(_) | Thrust(_) | Brake(_) | Strafe(_) | Fire(_) | RaiseShield(_) => return,
+			//Anything else isn't a ship control, so a stray action is ignored rather than bringing the server down.
+			_ => return,
+		};
+		self.events.push(self.tick, event);
+	}
+
+	//Runs every whole substep that fits, carrying the remainder over to the next update.
+	pub fn update(&mut self, timestep : f32) {
+		self.collisions.clear();
+		self.lag += timestep;
+		let substeps = (self.lag / physics::SUBSTEP) as u32;
+		self.lag = (self.lag - substeps as f32 * physics::SUBSTEP).max(0.0);
+		for _ in 0..substeps.min(physics::MAX_SUBSTEPS) {
+			self.step(physics::SUBSTEP);
+		}
+	}
+
+	fn step(&mut self, timestep : f32) {
+		self.tick += 1;
+		self.update_ships(timestep);
+		self.update_projectiles(timestep);
+		self.update_pilots();
+		self.movement(timestep);
+
+		self.asteroids.update(timestep);
+		if let Some(zone) = &mut self.zone {
+			zone.update(timestep);
+		}
+		self.update_capture(timestep);
+
+		self.collide(timestep);
+	}
+
+	fn update_capture(&mut self, timestep : f32) {
+		let point = match &self.capture {
+			Some(point) => point,
+			None => return,
+		};
+		let mut present = self.ships.ids()
+			.filter(|id| self.alive(*id) && self.pos(*id).map_or(false, |pos| point.contains(pos)))
+			.filter_map(|id| self.team_of(id))
+			.collect::<Vec<_>>();
+		present.sort();
+		present.dedup();
+		if let Some(team) = self.capture.as_mut().and_then(|point| point.update(timestep, &present)) {
+			self.events.push(self.tick, WorldEvent::Captured { team });
+		}
+	}
+
+	fn update_ships(&mut self, timestep : f32) {
+		let mut fired = vec![];
+		for (id, ship) in self.ships.iter_mut() {
+			let alive = self.healths.get(id).map_or(false, Health::alive);
+			let (transform, velocity, body) = match (self.transforms.get(id), self.velocities.get(id), self.bodies.get_mut(id)) {
+				(Some(transform), Some(velocity), Some(body)) => (transform, velocity, body),
+				_ => continue,
+			};
+			let class = find_class(&self.classes, &ship.class);
+			ship.update(timestep, alive, transform.angle, velocity, body, class, &self.ship_params);
+			//Every gun fires at once, each using up a round.
+			let rounds = class.weapon_slots.len() as f32;
+			if alive && ship.firing && ship.cooldown <= 0.0 && rounds > 0.0 && ship.inventory.take(Resource::Ammo, rounds) {
+				ship.cooldown = self.ship_params.fire_cooldown;
+				for slot in &class.weapon_slots {
+					fired.push((id, *transform, *velocity, *slot));
+				}
+			}
+		}
+
+		for (owner, transform, velocity, slot) in fired {
+			let spread = self.ship_params.projectile_spread;
+			let slot = WeaponSlot { angle : slot.angle + self.rng.gen_range(-spread, spread), ..slot };
+			let (transform, velocity) = Projectile::launch(&transform, &velocity, &slot, &self.ship_params);
+			let id = self.entities.spawn();
+			self.transforms.insert(id, transform);
+			self.velocities.insert(id, velocity);
+			self.owners.insert(id, Owner(owner));
+			self.sprites.insert(id, Projectile::SPRITE);
+			self.projectiles.insert(id, Projectile::new(&self.ship_params));
+			self.events.push(self.tick, WorldEvent::Fired { ship : owner, projectile : id });
+		}
+	}
+
+	fn update_projectiles(&mut self, timestep : f32) {
+		let mut expired = vec![];
+		for (id, projectile) in self.projectiles.iter_mut() {
+			projectile.life -= timestep;
+			if projectile.life <= 0.0 {
+				expired.push(id);
+			}
+		}
+		for id in expired {
+			self.despawn(id);
+		}
+	}
+
+	//Pilots on foot walk over the asteroid they stand on, carried along with it.
+	//Those left adrift when it breaks up have nothing to push off from.
+	fn update_pilots(&mut self) {
+		let walkers = self.pilots.iter()
+			.filter(|(_, pilot)| pilot.ship.is_none())
+			.map(|(id, pilot)| (id, pilot.walk_velocity(&self.ship_params)))
+			.collect::<Vec<_>>();
+		for (id, walk) in walkers {
+			let ground = match self.pos(id).and_then(|pos| self.surface(pos, Pilot::RADIUS)) {
+				Some(index) => self.asteroids.get(index).vel,
+				None => continue,
+			};
+			if let Some(velocity) = self.velocities.get_mut(id) {
+				velocity.vel = (ground.0 + walk.0, ground.1 + walk.1);
+			}
+			if let (Some(transform), true) = (self.transforms.get_mut(id), walk != (0.0, 0.0)) {
+				transform.angle = walk.1.atan2(walk.0).to_degrees();
+			}
+		}
+	}
+
+	//Integrates everything with a rigid body, and lets anything else with a velocity drift.
+	fn movement(&mut self, timestep : f32) {
+		for (id, velocity) in self.velocities.iter_mut() {
+			if let Some(transform) = self.transforms.get_mut(id) {
+				match self.bodies.get_mut(id) {
+					Some(body) => body.integrate(transform, velocity, timestep),
+					None => physics::drift(transform, velocity, timestep),
+				}
+			}
+		}
+	}
+
+	fn collide(&mut self, timestep : f32) {
+		let mut bodies = vec![];
+		//Sweeps start from where each body was at the start of the step.
+		let swept = |transform : &Transform, velocity : (f32, f32)| (transform.pos.0 - velocity.0 * timestep, transform.pos.1 - velocity.1 * timestep);
+
+		for (id, ship) in self.ships.iter() {
+			if let (Some(transform), Some(velocity), Some(body)) = (self.transforms.get(id), self.velocities.get(id), self.bodies.get(id)) {
+				let shape = self.class(&ship.class).shape();
+				bodies.push(Body::new(BodyKey::Ship(id), shape, swept(transform, velocity.vel), transform.pos, velocity.vel, transform.angle - 90.0).with_mass(body.mass));
+			}
+		}
+
+		for (id, _) in self.projectiles.iter() {
+			if let (Some(transform), Some(velocity)) = (self.transforms.get(id), self.velocities.get(id)) {
+				bodies.push(Body::new(BodyKey::Projectile(id), Projectile::SHAPE, swept(transform, velocity.vel), transform.pos, velocity.vel, 0.0).sensor());
+			}
+		}
+
+		for (id, _) in self.pickups.iter() {
+			if let Some(transform) = self.transforms.get(id) {
+				bodies.push(Body::new(BodyKey::Pickup(id), Pickup::SHAPE, transform.pos, transform.pos, (0.0, 0.0), 0.0).sensor());
+			}
+		}
+
+		for (id, _) in self.pilots.iter().filter(|(_, pilot)| pilot.ship.is_none()) {
+			if let (Some(transform), Some(velocity), true) = (self.transforms.get(id), self.velocities.get(id), self.alive(id)) {
+				bodies.push(Body::new(BodyKey::Pilot(id), Pilot::SHAPE, swept(transform, velocity.vel), transform.pos, velocity.vel, 0.0).fixed());
+			}
+		}
+
+		for (id, asteroid) in self.asteroids.iter() {
+			let pos = self.asteroids.pos(id);
+			let start = (pos.0 - asteroid.vel.0 * timestep, pos.1 - asteroid.vel.1 * timestep);
+			bodies.push(Body::new(BodyKey::Asteroid(id), asteroid.shape(), start, pos, asteroid.vel, 0.0).fixed());
+		}
+
+		let owners = &self.owners;
+		self.collisions.extend(collision::step(&mut bodies, COLLISION_CELL, |a, b| match (a.key, b.key) {
+			(BodyKey::Projectile(p), BodyKey::Ship(s)) | (BodyKey::Ship(s), BodyKey::Projectile(p)) => owners.get(p) != Some(&Owner(s)),
+			(BodyKey::Asteroid(_), BodyKey::Asteroid(_)) => false,
+			//Pilots on foot can be shot, but walk over asteroids and around ships.
+			(BodyKey::Projectile(_), BodyKey::Pilot(_)) | (BodyKey::Pilot(_), BodyKey::Projectile(_)) => true,
+			(BodyKey::Pilot(_), _) | (_, BodyKey::Pilot(_)) => false,
+			(BodyKey::Pickup(_), BodyKey::Ship(_)) | (BodyKey::Ship(_), BodyKey::Pickup(_)) => true,
+			(BodyKey::Pickup(_), _) | (_, BodyKey::Pickup(_)) => false,
+			_ => true,
+		}));
+
+		for body in &bodies {
+			if let BodyKey::Ship(id) = body.key {
+				let radius = self.class_of(id).size;
+				if let (Some(transform), Some(velocity)) = (self.transforms.get_mut(id), self.velocities.get_mut(id)) {
+					transform.pos = body.pos;
+					velocity.vel = body.vel;
+					self.arena.confine(&mut transform.pos, &mut velocity.vel, radius);
+				}
+			}
+		}
+
+		//Pilots who step off the edge of their asteroid are held at its rim.
+		for (id, _) in self.pilots.iter() {
+			if let (Some(transform), Some(velocity)) = (self.transforms.get_mut(id), self.velocities.get_mut(id)) {
+				let asteroids = &self.asteroids;
+				let nearest = asteroids.iter()
+					.map(|(index, asteroid)| (asteroids.pos(index), asteroid.radius))
+					.map(|(centre, radius)| (centre, radius, distance(centre, transform.pos)))
+					.min_by(|a, b| (a.2 - a.1).partial_cmp(&(b.2 - b.1)).unwrap_or(std::cmp::Ordering::Equal));
+				if let Some((centre, radius, gap)) = nearest.filter(|(_, radius, gap)| *gap >= radius + Pilot::RADIUS && *gap < radius + Pilot::RADIUS * 2.0) {
+					let pull = radius / gap;
+					transform.pos = (centre.0 + (transform.pos.0 - centre.0) * pull, centre.1 + (transform.pos.1 - centre.1) * pull);
+				}
+				self.arena.confine(&mut transform.pos, &mut velocity.vel, Pilot::RADIUS);
+			}
+		}
+
+		let mut stopped = vec![];
+		for (id, _) in self.projectiles.iter() {
+			if let (Some(transform), Some(velocity)) = (self.transforms.get_mut(id), self.velocities.get_mut(id)) {
+				if self.arena.confine(&mut transform.pos, &mut velocity.vel, 0.0) {
+					stopped.push(id);
+				}
+			}
+		}
+		for id in stopped {
+			self.despawn(id);
+		}
+	}
+
+	//Applies damage through the ship's shield, reporting a death caused by `killer`.
+	pub fn damage_ship(&mut self, id : EntityId, amount : f32, killer : Option<EntityId>) {
+		if let (Some(ship), Some(health)) = (self.ships.get_mut(id), self.healths.get_mut(id)) {
+			if health.alive() && ship.damage(health, amount, &self.ship_params) {
+				self.events.push(self.tick, WorldEvent::Died { ship : id, killer });
+			}
+		}
+	}
+
+	//Pilots on foot have no shield or respawn of their own. One that dies is put back aboard the nearest free ship
+	//as a wreck, so it respawns like any other ship and the death is scored against that ship.
+	pub fn damage_pilot(&mut self, id : EntityId, amount : f32, killer : Option<EntityId>) {
+		match self.healths.get_mut(id) {
+			Some(health) if health.alive() => health.hull = (health.hull - amount).max(0.0),
+			_ => return,
+		}
+		let pos = match self.pos(id) {
+			Some(pos) if !self.alive(id) => pos,
+			_ => return,
+		};
+		let wreck = self.ships.iter()
+			.filter(|(_, ship)| ship.pilot.is_none())
+			.filter_map(|(ship, _)| Some((ship, distance(self.pos(ship)?, pos))))
+			.min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+			.map(|(ship, _)| ship);
+		let wreck = match wreck {
+			Some(wreck) => wreck,
+			None => return,
+		};
+		self.seat(id, wreck);
+		if let (Some(ship), Some(health)) = (self.ships.get_mut(wreck), self.healths.get_mut(wreck)) {
+			health.hull = 0.0;
+			ship.respawn_timer = self.ship_params.respawn_delay;
+			ship.release_controls();
+		}
+		self.events.push(self.tick, WorldEvent::Died { ship : wreck, killer });
+	}
+
+	//Damages ships and pilots on foot caught outside a hazard arena or the safe zone, on the server only like hits.
+	//Crewed ships suffocate once out of oxygen, and pilots once adrift off any asteroid.
+	pub fn apply_hazards(&mut self, timestep : f32) {
+		let hazard = match self.arena.edge {
+			Edge::Hazard { damage } => damage,
+			_ => 0.0,
+		};
+		let exposure = |pos : (f32, f32), suffocating : bool| {
+			let mut damage = 0.0;
+			if !self.arena.contains(pos) {
+				damage += hazard;
+			}
+			if let Some(zone) = self.zone.as_ref().filter(|zone| !zone.contains(pos)) {
+				damage += zone.damage();
+			}
+			if suffocating {
+				damage += self.ship_params.suffocation;
+			}
+			damage
+		};
+
+		let mut ships = vec![];
+		for (id, ship) in self.ships.iter() {
+			match self.pos(id) {
+				Some(pos) if self.alive(id) => ships.push((id, exposure(pos, ship.pilot.is_some() && ship.inventory.oxygen <= 0.0))),
+				_ => continue,
+			}
+		}
+		let mut pilots = vec![];
+		for (id, _) in self.pilots.iter() {
+			match self.pos(id) {
+				Some(pos) if self.alive(id) => pilots.push((id, exposure(pos, self.surface(pos, Pilot::RADIUS).is_none()))),
+				_ => continue,
+			}
+		}
+
+		for (id, damage) in ships.into_iter().filter(|(_, damage)| *damage > 0.0) {
+			self.damage_ship(id, damage * timestep, None);
+		}
+		for (id, damage) in pilots.into_iter().filter(|(_, damage)| *damage > 0.0) {
+			self.damage_pilot(id, damage * timestep, None);
+		}
+	}
+
+	//Living ships take whatever fits in their inventory from the pickups they touched.
+	pub fn collect_pickups(&mut self) {
+		let mut emptied = vec![];
+		for event in &self.collisions {
+			if let (BodyKey::Pickup(p), BodyKey::Ship(s)) | (BodyKey::Ship(s), BodyKey::Pickup(p)) = (event.a, event.b) {
+				let alive = self.healths.get(s).map_or(false, Health::alive);
+				if let (Some(ship), Some(pickup), true) = (self.ships.get_mut(s), self.pickups.get_mut(p), alive) {
+					let taken = ship.inventory.add(pickup.kind, pickup.amount, &self.ship_params);
+					pickup.amount -= taken;
+					if taken > 0.0 {
+						self.events.push(self.tick, WorldEvent::PickedUp { ship : s, kind : pickup.kind, amount : taken });
+					}
+					if pickup.amount <= 0.0 {
+						emptied.push(p);
+					}
+				}
+			}
+		}
+		for id in emptied {
+			self.despawn(id);
+		}
+	}
+
+	pub fn spawn_pickup(&mut self, pickup : Pickup, pos : (f32, f32)) -> EntityId {
+		let id = self.entities.spawn();
+		self.transforms.insert(id, Transform { pos, angle : 0.0 });
+		self.sprites.insert(id, pickup.sprite());
+		self.pickups.insert(id, pickup);
+		id
+	}
+
+	//Keeps the arena stocked with fuel, ammo and oxygen, scrap only comes from asteroids.
+	pub fn scatter_pickups(&mut self) {
+		let (w, h) = (self.arena.half_size.0 * SPAWN_MARGIN, self.arena.half_size.1 * SPAWN_MARGIN);
+		let target = (4.0 * self.arena.half_size.0 * self.arena.half_size.1 * PICKUP_DENSITY) as usize;
+		let scattered = self.pickups.iter().filter(|(_, pickup)| pickup.kind != Resource::Scrap).count();
+		for _ in scattered..target {
+			let kind = [Resource::Fuel, Resource::Ammo, Resource::Oxygen][self.rng.gen_range(0, 3)];
+			let pos = (self.rng.gen_range(-w, w), self.rng.gen_range(-h, h));
+			self.spawn_pickup(Pickup::scattered(kind), pos);
+		}
+	}
+
+	//Only the server resolves hits, clients just see the results in the next snapshot.
+	pub fn resolve_hits(&mut self) {
+		let collisions = std::mem::take(&mut self.collisions);
+		for event in &collisions {
+			match (event.a, event.b) {
+				(BodyKey::Projectile(p), BodyKey::Ship(s)) | (BodyKey::Ship(s), BodyKey::Projectile(p)) => {
+					if let Some(projectile) = self.projectiles.get(p).cloned().filter(|_| self.alive(s)) {
+						let owner = self.owners.get(p).map(|owner| owner.0);
+						if let (Some(transform), Some(velocity)) = (self.transforms.get(p).copied(), self.velocities.get(p).copied()) {
+							self.knock_back(s, transform.pos, velocity.vel);
+						}
+						self.despawn(p);
+						let damage = self.shot_damage(owner, s, projectile.damage);
+						if damage > 0.0 {
+							self.events.push(self.tick, WorldEvent::Hit { ship : s, by : owner, damage });
+							self.damage_ship(s, damage, owner);
+						}
+					}
+				},
+				(BodyKey::Projectile(p), BodyKey::Pilot(pilot)) | (BodyKey::Pilot(pilot), BodyKey::Projectile(p)) => {
+					if let Some(projectile) = self.projectiles.get(p).cloned().filter(|_| self.alive(pilot)) {
+						let owner = self.owners.get(p).map(|owner| owner.0);
+						self.despawn(p);
+						let damage = self.shot_damage(owner, pilot, projectile.damage);
+						if damage > 0.0 {
+							self.events.push(self.tick, WorldEvent::Hit { ship : pilot, by : owner, damage });
+							self.damage_pilot(pilot, damage, owner);
+						}
+					}
+				},
+				(BodyKey::Projectile(p), BodyKey::Asteroid(a)) | (BodyKey::Asteroid(a), BodyKey::Projectile(p)) => {
+					if let Some(projectile) = self.projectiles.get(p).cloned().filter(|_| self.asteroids.get(a).alive()) {
+						self.despawn(p);
+						if let Some((asteroid, pos)) = self.asteroids.damage(a, projectile.damage, &mut self.rng) {
+							self.events.push(self.tick, WorldEvent::AsteroidDestroyed { pos, radius : asteroid.radius });
+							self.spawn_pickup(Pickup { kind : Resource::Scrap, amount : asteroid.scrap() }, pos);
+						}
+					}
+				},
+				_ => {},
+			}
+		}
+		self.collisions = collisions;
+		self.asteroids.clear_destroyed();
+	}
+
+	//Shots from teammates only do the friendly fire share of their damage.
+	fn shot_damage(&self, owner : Option<EntityId>, target : EntityId, damage : f32) -> f32 {
+		match (owner.and_then(|owner| self.team_of(owner)), self.team_of(target)) {
+			(Some(a), Some(b)) if a == b => damage * self.ship_params.friendly_fire,
+			_ => damage,
+		}
+	}
+
+	//Shoves a ship along `direction`, spinning it too when the hit lands off centre.
+	fn knock_back(&mut self, id : EntityId, point : (f32, f32), direction : (f32, f32)) {
+		let length = (direction.0 * direction.0 + direction.1 * direction.1).sqrt();
+		if length <= 0.0 {
+			return;
+		}
+		let strength = self.ship_params.projectile_impulse / length;
+		if let (Some(body), Some(transform), Some(velocity)) = (self.bodies.get(id), self.transforms.get(id), self.velocities.get_mut(id)) {
+			body.apply_impulse_at(transform, velocity, point, (direction.0 * strength, direction.1 * strength));
+		}
+	}
+
+	pub fn add_ship(&mut self, class : &str) -> EntityId {
+		let class = self.class(class).clone();
+		let id = self.entities.spawn();
+		self.transforms.insert(id, Transform::default());
+		self.velocities.insert(id, Velocity::default());
+		self.healths.insert(id, Health { hull : 0.0 });
+		self.sprites.insert(id, class.sprite());
+		self.ships.insert(id, Ship::new(&class, &self.ship_params));
+		self.respawn_ship(id);
+		id
+	}
+
+	//Swaps the ship's class and respawns it as one. Returns false if no such class is loaded.
+	pub fn set_class(&mut self, id : EntityId, name : &str) -> bool {
+		let class = match self.classes.iter().find(|class| class.name == name) {
+			Some(class) => class.clone(),
+			None => return false,
+		};
+		match self.ships.get_mut(id) {
+			Some(ship) => ship.class = class.name.clone(),
+			None => return false,
+		}
+		self.sprites.insert(id, class.sprite());
+		self.respawn_ship(id);
+		self.events.push(self.tick, WorldEvent::ClassChanged { ship : id, class : class.name });
+		true
+	}
+
+	fn respawn_ship(&mut self, id : EntityId) {
+		let pos = self.spawn_point(id);
+		let class = find_class(&self.classes, self.ships.get(id).map_or("", |ship| ship.class.as_str()));
+		if let Some(ship) = self.ships.get_mut(id) {
+			ship.respawn(class, &self.ship_params);
+		}
+		if let Some(health) = self.healths.get_mut(id) {
+			health.hull = class.hull;
+		}
+		if let Some(transform) = self.transforms.get_mut(id) {
+			transform.pos = pos;
+		}
+		if let Some(velocity) = self.velocities.get_mut(id) {
+			*velocity = Velocity::default();
+		}
+		self.bodies.insert(id, Ship::body(class, &self.ship_params));
+	}
+
+	//Clears the field for a new round: shots and loot are gone, the zone starts over,
+	//pilots on foot climb back into any free ship and every ship respawns fresh.
+	pub fn reset_round(&mut self) {
+		let loose = self.projectiles.ids().chain(self.pickups.ids()).collect::<Vec<_>>();
+		for id in loose {
+			self.despawn(id);
+		}
+		if let Some(zone) = &mut self.zone {
+			zone.restart(self.arena.half_size, self.rng.next_u64());
+		}
+		if let Some(point) = &mut self.capture {
+			point.restart();
+		}
+
+		let walkers = self.pilots.iter().filter(|(_, pilot)| pilot.ship.is_none()).map(|(id, _)| id).collect::<Vec<_>>();
+		for pilot in walkers {
+			let free = self.ships.iter().find(|(_, ship)| ship.pilot.is_none()).map(|(id, _)| id);
+			match free {
+				Some(ship) => self.embark(pilot, ship),
+				None => break,
+			}
+		}
+
+		let ships = self.ships.ids().collect::<Vec<_>>();
+		for id in ships {
+			if let Some(ship) = self.ships.get_mut(id) {
+				ship.release_controls();
+			}
+			self.respawn_ship(id);
+			self.events.push(self.tick, WorldEvent::Respawned { ship : id });
+		}
+	}
+
+	//Like hit detection, respawning is decided by the server alone.
+	pub fn respawn_ships(&mut self) {
+		let ready = self.ships.iter()
+			.filter(|(id, ship)| !self.alive(*id) && ship.respawn_timer <= 0.0)
+			.map(|(id, _)| id)
+			.collect::<Vec<_>>();
+		for id in ready {
+			self.respawn_ship(id);
+			self.events.push(self.tick, WorldEvent::Respawned { ship : id });
+		}
+	}
+
+	//Picks the candidate point furthest from every other living ship and asteroid.
+	pub fn spawn_point(&mut self, exclude : EntityId) -> (f32, f32) {
+		let (w, h) = (self.arena.half_size.0 * SPAWN_MARGIN, self.arena.half_size.1 * SPAWN_MARGIN);
+		let rng = &mut self.rng;
+		let candidates = (0..16).map(|_| (rng.gen_range(-w, w), rng.gen_range(-h, h))).collect::<Vec<_>>();
+
+		let clearance = |point : &(f32, f32)| {
+			let ships = self.ships.ids()
+				.filter(|id| *id != exclude && self.alive(*id))
+				.filter_map(|id| self.pos(id))
+				.map(|pos| distance(pos, *point));
+			let asteroids = self.asteroids.iter()
+				.map(|(id, asteroid)| distance(self.asteroids.pos(id), *point) - asteroid.radius);
+			ships.chain(asteroids).fold(std::f32::MAX, f32::min)
+		};
+
+		candidates.into_iter()
+			.max_by(|a, b| clearance(a).partial_cmp(&clearance(b)).unwrap_or(std::cmp::Ordering::Equal))
+			.unwrap()
+	}
+
+	//Which animation an entity's sprite plays, and how many seconds into it.
+	//Looping animations run off the world clock, so they stay in step between snapshots.
+	fn animation(&self, id : EntityId) -> (Animation, f32) {
+		let clock = self.tick as f32 * physics::SUBSTEP;
+		let (ship, health) = match (self.ships.get(id), self.healths.get(id)) {
+			(Some(ship), Some(health)) => (ship, health),
+			_ => return (Animation::Idle, clock),
+		};
+		if !health.alive() {
+			(Animation::Exploding, self.ship_params.respawn_delay - ship.respawn_timer)
+		} else if ship.thrusting {
+			(Animation::Thrusting, clock)
+		} else if health.hull < self.class(&ship.class).hull * DAMAGED_HULL {
+			(Animation::Damaged, clock)
+		} else {
+			(Animation::Idle, clock)
+		}
+	}
+
+	//The render system, drawing every entity with a sprite and a transform over the arena and its asteroids.
+	pub fn render_to(&self, output_buffer : &mut Vec<Instance2D>, texture_map : &FnvHashMap<ClientTexture, GLvec4>, sheets : &FnvHashMap<ClientTexture, SpriteSheet>) {
+		let flat_text = texture_map[&ClientTexture::Flat];
+		self.arena.render_to(output_buffer, flat_text);
+		if let Some(zone) = &self.zone {
+			zone.render_to(output_buffer, flat_text);
+		}
+		if let Some(point) = &self.capture {
+			point.render_to(output_buffer, flat_text, &self.teams);
+		}
+		self.asteroids.render_to(output_buffer, flat_text);
+
+		for (id, sprite) in self.sprites.iter() {
+			if let Some(transform) = self.transforms.get(id) {
+				let mut instance = Instance2D::default();
+				let (r, g, b, a) = sprite.tint;
+				let (tr, tg, tb, ta) = self.team_tint(id).unwrap_or((1.0, 1.0, 1.0, 1.0));
+				//Wrecks and freshly spawned ships are dimmed, but keep their team colour.
+				let (sr, sg, sb, sa) = match self.ships.get(id) {
+					Some(_) if !self.alive(id) => (0.35, 0.3, 0.3, 0.6),
+					Some(ship) if ship.protection > 0.0 => (0.5, 0.5, 0.5, 0.5),
+					_ => (1.0, 1.0, 1.0, 1.0),
+				};
+				instance.color_tint = GLvec4(r * tr * sr, g * tg * sg, b * tb * sb, a * ta * sa);
+				instance.texture_coords = match sheets.get(&sprite.texture) {
+					Some(sheet) => {
+						let (animation, time) = self.animation(id);
+						sheet.frame(animation, time)
+					},
+					None => texture_map[&sprite.texture],
+				};
+				instance.translate = GLvec2(transform.pos.0, transform.pos.1);
+				instance.scale = GLvec2(sprite.scale.0, sprite.scale.1);
+				instance.rotation = GLfloat(transform.angle + sprite.rotation);
+				output_buffer.push(instance);
+			}
+		}
+
+		let shield_text = texture_map[&ClientTexture::Shield];
+		output_buffer.extend(
+			self.ships.iter().filter_map(|(id, ship)| ship.render_shield(shield_text, self.transforms.get(id)?, self.class(&ship.class)))
+		);
+	}
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct ShipParams {
+	pub max_speed          : f32,
+	pub strafe_thrust      : f32,
+	pub brake              : f32,
+	pub drag               : f32,
+	pub angular_damping    : f32,
+	pub fire_cooldown      : f32,
+	pub projectile_speed   : f32,
+	pub projectile_life    : f32,
+	pub projectile_damage  : f32,
+	//Momentum a hit knocks into the ship it strikes.
+	pub projectile_impulse : f32,
+	//Shots stray up to this many degrees either side of where their gun points.
+	pub projectile_spread  : f32,
+	//Share of their damage that shots do to the shooter's own team.
+	pub friendly_fire      : f32,
+	//Pilots on foot.
+	pub walk_speed         : f32,
+	//How close to a ship's hull a pilot has to be to climb in.
+	pub board_range        : f32,
+	//Fastest a ship can be moving, against the asteroid it's docked to, for its pilot to get out.
+	pub leave_speed        : f32,
+	//Health of a pilot on foot, who has no hull or shield to hide behind.
+	pub pilot_health       : f32,
+	pub shield_regen       : f32,
+	pub shield_cooldown    : f32,
+	pub max_energy         : f32,
+	pub energy_regen       : f32,
+	pub shield_drain       : f32,
+	pub respawn_delay      : f32,
+	pub spawn_protection   : f32,
+	pub fuel_capacity      : f32,
+	pub ammo_capacity      : f32,
+	pub scrap_capacity     : f32,
+	pub oxygen_capacity    : f32,
+	//Fuel burnt per second of thrust or strafing.
+	pub fuel_use           : f32,
+	//Oxygen breathed per second while alive.
+	pub oxygen_use         : f32,
+	//Hull damage per second once the oxygen has run out.
+	pub suffocation        : f32,
+}
+
+impl Default for ShipParams {
+	fn default() -> Self {
+		Self {
+			max_speed          : 1.5,
+			strafe_thrust      : 1.2,
+			brake              : 3.0,
+			drag               : 0.5,
+			angular_damping    : 12.0,
+			fire_cooldown      : 0.25,
+			projectile_speed   : 3.0,
+			projectile_life    : 1.0,
+			projectile_damage  : 10.0,
+			projectile_impulse : 0.3,
+			projectile_spread  : 2.0,
+			friendly_fire      : 0.0,
+			walk_speed         : 0.6,
+			board_range        : 0.3,
+			leave_speed        : 0.1,
+			pilot_health       : 20.0,
+			shield_regen       : 8.0,
+			shield_cooldown    : 3.0,
+			max_energy         : 100.0,
+			energy_regen       : 15.0,
+			shield_drain       : 20.0,
+			respawn_delay      : 5.0,
+			spawn_protection   : 2.0,
+			fuel_capacity      : 100.0,
+			ammo_capacity      : 60.0,
+			scrap_capacity     : 50.0,
+			oxygen_capacity    : 100.0,
+			fuel_use           : 5.0,
+			oxygen_use         : 1.0,
+			suffocation        : 10.0,
+		}
+	}
+}
+
+//What a pilot is holding down, whether that's a player's keys or a bot's decisions.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Controls {
+	pub turning   : i8,
+	pub strafing  : i8,
+	pub thrusting : bool,
+	pub braking   : bool,
+	pub firing    : bool,
+	pub shielding : bool,
+}
+
+impl Controls {
+	//The actions that bring the ship's current controls in line with these.
+	pub fn actions_for(&self, ship : &Ship) -> Vec<comms::Action> {
+		use comms::Action::*;
+		let mut actions = vec![];
+		if self.turning != ship.turning {
+			actions.push(TurnShip(self.turning));
+		}
+		if self.strafing != ship.strafing {
+			actions.push(Strafe(self.strafing));
+		}
+		if self.thrusting != ship.thrusting {
+			actions.push(Thrust(self.thrusting));
+		}
+		if self.braking != ship.braking {
+			actions.push(Brake(self.braking));
+		}
+		if self.firing != ship.firing {
+			actions.push(Fire(self.firing));
+		}
+		if self.shielding != ship.shielding {
+			actions.push(RaiseShield(self.shielding));
+		}
+		actions
+	}
+}
+
+fn distance(a : (f32, f32), b : (f32, f32)) -> f32 {
+	((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn find_class<'a>(classes : &'a [ShipClass], name : &str) -> &'a ShipClass {
+	classes.iter().find(|class| class.name == name).unwrap_or(&classes[0])
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Ship {
+	pub class : String,
+	//The pilot flying this ship, ships without one sit idle until somebody boards.
+	pub pilot : Option<EntityId>,
+	pub turning : i8,
+	pub thrusting : bool,
+	pub braking : bool,
+	pub strafing : i8,
+	pub firing : bool,
+	pub cooldown : f32,
+	pub shielding : bool,
+	pub shield : f32,
+	pub shield_cooldown : f32,
+	pub energy : f32,
+	pub respawn_timer : f32,
+	pub protection : f32,
+	pub inventory : Inventory,
+}
+
+impl Ship {
+
+	pub fn new(class : &ShipClass, params : &ShipParams) -> Self {
+		Self {
+			class : class.name.clone(),
+			pilot : None,
+			turning : 0i8,
+			thrusting : false,
+			braking : false,
+			strafing : 0i8,
+			firing : false,
+			cooldown : 0.0,
+			shielding : false,
+			shield : class.shield,
+			shield_cooldown : 0.0,
+			energy : params.max_energy,
+			respawn_timer : 0.0,
+			protection : 0.0,
+			inventory : Inventory::loadout(params),
+		}
+	}
+
+	//The body a ship of this class flies with, at rest.
+	pub fn body(class : &ShipClass, params : &ShipParams) -> RigidBody {
+		RigidBody::boxed(class.mass, class.half_extents())
+			.with_drag(params.drag, params.angular_damping)
+			.with_max_speed(params.max_speed)
+	}
+
+	//Turns the pilot's controls into forces on the ship's body, which the physics step then integrates.
+	pub fn update(&mut self, timestep : f32, alive : bool, angle : f32, velocity : &Velocity, body : &mut RigidBody, class : &ShipClass, params : &ShipParams) {
+
+		self.cooldown = (self.cooldown - timestep).max(0.0);
+		self.respawn_timer = (self.respawn_timer - timestep).max(0.0);
+		self.protection = (self.protection - timestep).max(0.0);
+		self.shield_cooldown = (self.shield_cooldown - timestep).max(0.0);
+
+		if self.shield_active() {
+			self.energy = (self.energy - params.shield_drain * timestep).max(0.0);
+		} else {
+			self.energy = (self.energy + params.energy_regen * timestep).min(params.max_energy);
+			if self.shield_cooldown <= 0.0 {
+				self.shield = (self.shield + params.shield_regen * timestep).min(class.shield);
+			}
+		}
+
+		//Full turning torque is balanced by angular drag once the ship spins at its class's turn rate.
+		body.apply_torque(self.turning as f32 * class.turn_rate * body.angular_drag * body.inertia);
+
+		//Only whoever's aboard breathes, an empty ship keeps its air for the next pilot.
+		if alive && self.pilot.is_some() {
+			self.inventory.drain(Resource::Oxygen, params.oxygen_use * timestep);
+		}
+
+		//Engines only fire while there is fuel left to burn.
+		let burning = (self.thrusting || self.strafing != 0) && self.inventory.drain(Resource::Fuel, params.fuel_use * timestep);
+
+		let (sin, cos) = angle.to_radians().sin_cos();
+		let thrust = if self.thrusting && burning { class.thrust } else { 0.0 };
+		let strafe = if burning { self.strafing as f32 * params.strafe_thrust } else { 0.0 };
+		body.apply_force((
+			cos * thrust + sin * strafe,
+			sin * thrust - cos * strafe,
+		));
+
+		//Brakes push against the ship's motion, but never hard enough to send it backwards.
+		let vel = velocity.vel;
+		let speed = (vel.0 * vel.0 + vel.1 * vel.1).sqrt();
+		if self.braking && speed > 0.0 {
+			let brake = params.brake.min(speed / timestep) * body.mass / speed;
+			body.apply_force((-vel.0 * brake, -vel.1 * brake));
+		}
+	}
+
+	//Pilots can't raise shields while dead, so this only holds for living ships.
+	pub fn shield_active(&self) -> bool {
+		self.shielding && self.shield > 0.0 && self.energy > 0.0 && self.shield_cooldown <= 0.0
+	}
+
+	//Returns whether this damage destroyed the ship.
+	pub fn damage(&mut self, health : &mut Health, mut amount : f32, params : &ShipParams) -> bool {
+		if self.protection > 0.0 {
+			return false;
+		}
+
+		if self.shield_active() {
+			let absorbed = amount.min(self.shield);
+			self.shield -= absorbed;
+			amount -= absorbed;
+			if self.shield <= 0.0 {
+				self.shield_cooldown = params.shield_cooldown;
+			}
+		}
+
+		let was_alive = health.alive();
+		health.hull = (health.hull - amount).max(0.0);
+		if was_alive && !health.alive() {
+			self.respawn_timer = params.respawn_delay;
+			self.release_controls();
+			return true;
+		}
+		false
+	}
+
+	//Lets go of every control, for when the pilot dies or gets out.
+	pub fn release_controls(&mut self) {
+		self.turning = 0;
+		self.thrusting = false;
+		self.braking = false;
+		self.strafing = 0;
+		self.firing = false;
+		self.shielding = false;
+	}
+
+	//Resets everything but the ship's transform, velocity and health, which the world resets alongside.
+	pub fn respawn(&mut self, class : &ShipClass, params : &ShipParams) {
+		self.shield = class.shield;
+		self.shield_cooldown = 0.0;
+		self.energy = params.max_energy;
+		self.respawn_timer = 0.0;
+		self.protection = params.spawn_protection;
+		self.inventory = Inventory::loadout(params);
+	}
+
+	pub fn render_shield(&self, text_coords : GLvec4, transform : &Transform, class : &ShipClass) -> Option<Instance2D> {
+		if !self.shield_active() {
+			return None;
+		}
+		let alpha = 0.2 + 0.6 * self.shield / class.shield;
+		let mut instance = Instance2D::default();
+		instance.color_tint = GLvec4(0.5 * alpha, 0.8 * alpha, alpha, alpha);
+		instance.texture_coords = text_coords;
+		instance.translate = GLvec2(transform.pos.0, transform.pos.1);
+		instance.scale = GLvec2(class.size * 1.5, class.size * 1.5);
+		instance.rotation = GLfloat(transform.angle - 90.0);
+		Some(instance)
+	}
+}
@@ -0,0 +1,104 @@
+use serde_derive::*;
+
+use super::{Transform, Velocity};
+
+//The world always advances by whole substeps, so client and server integrate the same steps whatever their frame rates.
+pub const SUBSTEP      : f32 = 1.0 / 120.0;
+//Beyond this many substeps in one update the rest are dropped, so a long stall doesn't freeze the game catching up.
+pub const MAX_SUBSTEPS : u32 = 12;
+
+//Forces and torques pile up during a substep and are spent by `integrate`.
+#[derive(Serialize, Clone, Copy, Deserialize, Debug)]
+pub struct RigidBody {
+	pub mass         : f32,
+	//Moment of inertia about the centre, in mass times square units.
+	pub inertia      : f32,
+	//Rates at which velocity and spin decay by themselves, per second.
+	pub drag         : f32,
+	pub angular_drag : f32,
+	pub max_speed    : Option<f32>,
+	pub force        : (f32, f32),
+	//Degrees per second squared times inertia, like spin is kept in degrees.
+	pub torque       : f32,
+}
+
+impl RigidBody {
+	//A solid box with the given half extents.
+	pub fn boxed(mass : f32, half_extents : (f32, f32)) -> Self {
+		let (x, y) = half_extents;
+		Self {
+			mass,
+			inertia : mass * (x * x + y * y) / 3.0,
+			drag : 0.0,
+			angular_drag : 0.0,
+			max_speed : None,
+			force : (0.0, 0.0),
+			torque : 0.0,
+		}
+	}
+
+	pub fn with_drag(mut self, drag : f32, angular_drag : f32) -> Self {
+		self.drag = drag;
+		self.angular_drag = angular_drag;
+		self
+	}
+
+	pub fn with_max_speed(mut self, max_speed : f32) -> Self {
+		self.max_speed = Some(max_speed);
+		self
+	}
+
+	pub fn apply_force(&mut self, force : (f32, f32)) {
+		self.force.0 += force.0;
+		self.force.1 += force.1;
+	}
+
+	pub fn apply_torque(&mut self, torque : f32) {
+		self.torque += torque;
+	}
+
+	//Impulses change velocity straight away, rather than over the substep like forces.
+	pub fn apply_impulse(&self, velocity : &mut Velocity, impulse : (f32, f32)) {
+		velocity.vel.0 += impulse.0 / self.mass;
+		velocity.vel.1 += impulse.1 / self.mass;
+	}
+
+	//An impulse through `point` also sets the body spinning, unless it passes through the centre.
+	pub fn apply_impulse_at(&self, transform : &Transform, velocity : &mut Velocity, point : (f32, f32), impulse : (f32, f32)) {
+		self.apply_impulse(velocity, impulse);
+		let arm = (point.0 - transform.pos.0, point.1 - transform.pos.1);
+		let angular = arm.0 * impulse.1 - arm.1 * impulse.0;
+		velocity.spin += (angular / self.inertia).to_degrees();
+	}
+
+	//Semi-implicit Euler: velocities are updated first and positions move by the new velocities, which keeps orbits and springs stable.
+	pub fn integrate(&mut self, transform : &mut Transform, velocity : &mut Velocity, timestep : f32) {
+		velocity.vel.0 += self.force.0 / self.mass * timestep;
+		velocity.vel.1 += self.force.1 / self.mass * timestep;
+		velocity.spin += self.torque / self.inertia * timestep;
+
+		let drag = (-self.drag * timestep).exp();
+		velocity.vel.0 *= drag;
+		velocity.vel.1 *= drag;
+		velocity.spin *= (-self.angular_drag * timestep).exp();
+
+		if let Some(max_speed) = self.max_speed {
+			let speed = (velocity.vel.0 * velocity.vel.0 + velocity.vel.1 * velocity.vel.1).sqrt();
+			if speed > max_speed {
+				velocity.vel.0 *= max_speed / speed;
+				velocity.vel.1 *= max_speed / speed;
+			}
+		}
+
+		self.force = (0.0, 0.0);
+		self.torque = 0.0;
+		drift(transform, velocity, timestep);
+	}
+}
+
+//Moves bodies without mass, like projectiles, which only ever coast.
+pub fn drift(transform : &mut Transform, velocity : &Velocity, timestep : f32) {
+	transform.pos.0 += velocity.vel.0 * timestep;
+	transform.pos.1 += velocity.vel.1 * timestep;
+	transform.angle = (transform.angle + velocity.spin * timestep) % 360.0;
+}
@@ -0,0 +1,51 @@
+use serde_derive::*;
+
+use crate::reng::types::*;
+use crate::client::state::ClientTexture;
+use super::Sprite;
+use super::collision::Shape;
+use super::inventory::Resource;
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Pickup {
+	pub kind   : Resource,
+	pub amount : f32,
+}
+
+impl Pickup {
+
+	pub const SHAPE : Shape = Shape::Circle { radius : 0.1 };
+
+	//How much of each resource a scattered pickup holds.
+	pub fn scattered(kind : Resource) -> Self {
+		let amount = match kind {
+			Resource::Fuel => 25.0,
+			Resource::Ammo => 15.0,
+			Resource::Scrap => 5.0,
+			Resource::Oxygen => 25.0,
+		};
+		Self { kind, amount }
+	}
+
+	pub fn sprite(&self) -> Sprite {
+		let GLvec4(r, g, b, a) = self.kind.tint();
+		Sprite {
+			texture : ClientTexture::Flat,
+			tint : (r, g, b, a),
+			scale : (0.06, 0.06),
+			rotation : 45.0,
+		}
+	}
+}
+
+impl Resource {
+	//Shared with the hud, so each resource has the same colour on the field and on the inventory bars.
+	pub fn tint(self) -> GLvec4 {
+		match self {
+			Resource::Fuel => GLvec4(0.9, 0.5, 0.1, 1.0),
+			Resource::Ammo => GLvec4(1.0, 0.85, 0.3, 1.0),
+			Resource::Scrap => GLvec4(0.6, 0.6, 0.6, 1.0),
+			Resource::Oxygen => GLvec4(0.3, 0.7, 1.0, 1.0),
+		}
+	}
+}
@@ -0,0 +1,48 @@
+use serde_derive::*;
+
+use crate::client::state::ClientTexture;
+use super::{ShipParams, Sprite};
+use super::entity::EntityId;
+use super::collision::Shape;
+
+//Every player and bot is a pilot, flying a ship or out walking.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Pilot {
+	//The ship being flown. Pilots aboard have no transform, velocity or sprite of their own.
+	pub ship    : Option<EntityId>,
+	//Walking direction in world axes, each -1, 0 or 1.
+	pub walking : (i8, i8),
+	//Index into the world's teams, none outside team modes.
+	pub team    : Option<usize>,
+}
+
+impl Pilot {
+
+	pub const RADIUS : f32 = 0.06;
+	pub const SHAPE  : Shape = Shape::Circle { radius : Pilot::RADIUS };
+	pub const SPRITE : Sprite = Sprite {
+		texture : ClientTexture::Player,
+		tint : (1.0, 1.0, 1.0, 1.0),
+		scale : (0.12, 0.12),
+		rotation : -90.0,
+	};
+
+	pub fn aboard(ship : EntityId) -> Self {
+		Self {
+			ship : Some(ship),
+			walking : (0, 0),
+			team : None,
+		}
+	}
+
+	//Pilots walk at a steady pace with no momentum of their own, on top of whatever they stand on.
+	pub fn walk_velocity(&self, params : &ShipParams) -> (f32, f32) {
+		let (x, y) = (self.walking.0 as f32, self.walking.1 as f32);
+		let length = (x * x + y * y).sqrt();
+		if length > 0.0 {
+			(x / length * params.walk_speed, y / length * params.walk_speed)
+		} else {
+			(0.0, 0.0)
+		}
+	}
+}
@@ -0,0 +1,42 @@
+use serde_derive::*;
+
+use crate::client::state::ClientTexture;
+use super::{ShipParams, Transform, Velocity, Sprite, WeaponSlot};
+use super::collision::Shape;
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Projectile {
+	pub life   : f32,
+	pub damage : f32,
+}
+
+impl Projectile {
+
+	pub const SHAPE  : Shape = Shape::Circle { radius : 0.02 };
+	pub const SPRITE : Sprite = Sprite {
+		texture : ClientTexture::Flat,
+		tint : (1.0, 0.85, 0.3, 1.0),
+		scale : (0.015, 0.05),
+		rotation : -90.0,
+	};
+
+	pub fn new(params : &ShipParams) -> Self {
+		Self {
+			life : params.projectile_life,
+			damage : params.projectile_damage,
+		}
+	}
+
+	//Where a projectile fired from one of a ship's guns starts and how fast it goes, facing along its flight.
+	pub fn launch(ship : &Transform, ship_velocity : &Velocity, slot : &WeaponSlot, params : &ShipParams) -> (Transform, Velocity) {
+		let (sin, cos) = ship.angle.to_radians().sin_cos();
+		let muzzle = (ship.pos.0 + cos * slot.offset.0 - sin * slot.offset.1, ship.pos.1 + sin * slot.offset.0 + cos * slot.offset.1);
+		let (sin, cos) = (ship.angle + slot.angle).to_radians().sin_cos();
+		let vel = (ship_velocity.vel.0 + cos * params.projectile_speed, ship_velocity.vel.1 + sin * params.projectile_speed);
+		let transform = Transform {
+			pos : muzzle,
+			angle : vel.1.atan2(vel.0).to_degrees(),
+		};
+		(transform, Velocity { vel, spin : 0.0 })
+	}
+}
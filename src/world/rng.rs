@@ -0,0 +1,58 @@
+use serde_derive::*;
+
+//The default stream of PCG32, picked when a generator is seeded without one.
+const DEFAULT_STREAM : u64 = 1442695040888963407;
+const MULTIPLIER     : u64 = 6364136223846793005;
+
+//A PCG32 generator. Unlike `StdRng` its whole state is two numbers, so it travels with snapshots and saves,
+//and client prediction and replays draw exactly the numbers the server did.
+#[derive(Serialize, Clone, Deserialize, PartialEq, Debug)]
+pub struct WorldRng {
+	state : u64,
+	inc   : u64,
+}
+
+impl WorldRng {
+	pub fn new(seed : u64) -> Self {
+		let mut rng = Self { state : 0, inc : DEFAULT_STREAM | 1 };
+		rng.step();
+		rng.state = rng.state.wrapping_add(seed);
+		rng.step();
+		rng
+	}
+
+	//Seeds a separate generator from this one, for things like bots that draw numbers outside the world.
+	pub fn fork(&mut self) -> Self {
+		use rand::RngCore;
+		Self::new(self.next_u64())
+	}
+
+	fn step(&mut self) {
+		self.state = self.state.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+	}
+}
+
+impl rand::RngCore for WorldRng {
+	fn next_u32(&mut self) -> u32 {
+		let old = self.state;
+		self.step();
+		let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+		xorshifted.rotate_right((old >> 59) as u32)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		(self.next_u32() as u64) << 32 | self.next_u32() as u64
+	}
+
+	fn fill_bytes(&mut self, dest : &mut [u8]) {
+		for chunk in dest.chunks_mut(4) {
+			let bytes = self.next_u32().to_le_bytes();
+			chunk.copy_from_slice(&bytes[..chunk.len()]);
+		}
+	}
+
+	fn try_fill_bytes(&mut self, dest : &mut [u8]) -> Result<(), rand::Error> {
+		self.fill_bytes(dest);
+		Ok(())
+	}
+}
@@ -0,0 +1,22 @@
+use serde_derive::*;
+
+//One side in a team mode. Ships and pilots on foot show their team's colour over their own tint.
+#[derive(Serialize, Clone, Deserialize, PartialEq, Debug)]
+pub struct Team {
+	pub name : String,
+	pub tint : (f32, f32, f32, f32),
+}
+
+const COLOURS : [(&str, (f32, f32, f32, f32)); 4] = [
+	("red", (1.0, 0.45, 0.4, 1.0)),
+	("blue", (0.45, 0.6, 1.0, 1.0)),
+	("green", (0.5, 1.0, 0.5, 1.0)),
+	("yellow", (1.0, 0.9, 0.4, 1.0)),
+];
+
+pub const MAX_TEAMS : usize = COLOURS.len();
+
+//Up to four teams, none for free for all.
+pub fn default_teams(count : usize) -> Vec<Team> {
+	COLOURS.iter().take(count).map(|(name, tint)| Team { name : String::from(*name), tint : *tint }).collect()
+}
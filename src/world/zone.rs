@@ -0,0 +1,157 @@
+use serde_derive::*;
+use rand::Rng;
+
+use crate::reng::types::*;
+use crate::client::types::Instance2D;
+use super::rng::WorldRng;
+
+const RING_SEGMENTS : usize = 64;
+const RING_WIDTH    : f32 = 0.04;
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct ZonePhase {
+	//Seconds the zone holds still before this phase's shrink starts.
+	pub wait   : f32,
+	//Seconds the shrink takes.
+	pub shrink : f32,
+	//Radius at the end of the phase, as a fraction of the starting radius.
+	pub radius : f32,
+	//Damage per second to ships outside the zone during this phase.
+	pub damage : f32,
+}
+
+pub fn default_schedule() -> Vec<ZonePhase> {
+	vec![
+		ZonePhase { wait : 60.0, shrink : 30.0, radius : 0.6, damage : 2.0 },
+		ZonePhase { wait : 45.0, shrink : 25.0, radius : 0.35, damage : 5.0 },
+		ZonePhase { wait : 30.0, shrink : 20.0, radius : 0.15, damage : 10.0 },
+		ZonePhase { wait : 20.0, shrink : 15.0, radius : 0.0, damage : 20.0 },
+	]
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug)]
+pub struct Zone {
+	pub schedule     : Vec<ZonePhase>,
+	pub start_center : (f32, f32),
+	pub start_radius : f32,
+	//Where the last circle closes, picked from the match seed and again every round.
+	pub final_center : (f32, f32),
+	pub phase        : usize,
+	//Seconds spent in the current phase.
+	pub elapsed      : f32,
+}
+
+impl Zone {
+	//Starts as the circle around the arena, so nobody begins the match outside it.
+	pub fn new(schedule : Vec<ZonePhase>, half_size : (f32, f32), seed : u64) -> Self {
+		let start_radius = (half_size.0 * half_size.0 + half_size.1 * half_size.1).sqrt();
+		let final_radius = schedule.last().map_or(1.0, |phase| phase.radius) * start_radius;
+
+		//Keeping the final centre within this distance keeps every circle inside the one before it.
+		let reach = (start_radius - final_radius).min(half_size.0.min(half_size.1) - final_radius).max(0.0);
+		let mut rng = WorldRng::new(seed);
+		let angle = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
+		let distance = reach * rng.gen_range(0.0f32, 1.0).sqrt();
+
+		Self {
+			schedule,
+			start_center : (0.0, 0.0),
+			start_radius,
+			final_center : (angle.cos() * distance, angle.sin() * distance),
+			phase : 0,
+			elapsed : 0.0,
+		}
+	}
+
+	//Back to the full circle for a new round on the same schedule, closing somewhere new so the last safe spot can't be learnt.
+	pub fn restart(&mut self, half_size : (f32, f32), seed : u64) {
+		*self = Self::new(std::mem::take(&mut self.schedule), half_size, seed);
+	}
+
+	pub fn update(&mut self, timestep : f32) {
+		self.elapsed += timestep;
+		while let Some(phase) = self.schedule.get(self.phase) {
+			let length = phase.wait + phase.shrink;
+			if self.elapsed < length {
+				break;
+			}
+			self.elapsed -= length;
+			self.phase += 1;
+		}
+	}
+
+	//The circle with the given fraction of the starting radius, on the line towards the final centre.
+	fn circle(&self, radius : f32) -> ((f32, f32), f32) {
+		let final_radius = self.schedule.last().map_or(1.0, |phase| phase.radius);
+		let t = if final_radius < 1.0 { (1.0 - radius) / (1.0 - final_radius) } else { 0.0 };
+		let center = (
+			self.start_center.0 + (self.final_center.0 - self.start_center.0) * t,
+			self.start_center.1 + (self.final_center.1 - self.start_center.1) * t,
+		);
+		(center, radius * self.start_radius)
+	}
+
+	fn phase_radius(&self, phase : usize) -> f32 {
+		match phase {
+			0 => 1.0,
+			_ => self.schedule.get(phase - 1).map_or(1.0, |phase| phase.radius),
+		}
+	}
+
+	pub fn current(&self) -> ((f32, f32), f32) {
+		let from = self.phase_radius(self.phase);
+		let radius = match self.schedule.get(self.phase) {
+			Some(phase) if self.elapsed > phase.wait => {
+				let progress = ((self.elapsed - phase.wait) / phase.shrink).min(1.0);
+				from + (phase.radius - from) * progress
+			},
+			_ => from,
+		};
+		self.circle(radius)
+	}
+
+	//The circle the zone is shrinking towards, if there is one left.
+	pub fn next(&self) -> Option<((f32, f32), f32)> {
+		self.schedule.get(self.phase).map(|phase| self.circle(phase.radius))
+	}
+
+	//Seconds until the next shrink starts, none while shrinking or once the last circle has closed.
+	pub fn countdown(&self) -> Option<f32> {
+		self.schedule.get(self.phase)
+			.map(|phase| phase.wait - self.elapsed)
+			.filter(|remaining| *remaining > 0.0)
+	}
+
+	pub fn damage(&self) -> f32 {
+		self.schedule.get(self.phase).or_else(|| self.schedule.last()).map_or(0.0, |phase| phase.damage)
+	}
+
+	pub fn contains(&self, pos : (f32, f32)) -> bool {
+		let (center, radius) = self.current();
+		(pos.0 - center.0).powi(2) + (pos.1 - center.1).powi(2) <= radius * radius
+	}
+
+	pub fn render_to(&self, output_buffer : &mut Vec<Instance2D>, text_coords : GLvec4) {
+		let (center, radius) = self.current();
+		ring(output_buffer, text_coords, center, radius, GLvec4(0.2, 0.6, 1.0, 0.8));
+		if let Some((center, radius)) = self.next() {
+			ring(output_buffer, text_coords, center, radius, GLvec4(1.0, 1.0, 1.0, 0.4));
+		}
+	}
+}
+
+pub fn ring(output_buffer : &mut Vec<Instance2D>, text_coords : GLvec4, center : (f32, f32), radius : f32, tint : GLvec4) {
+	let step = std::f32::consts::PI * 2.0 / RING_SEGMENTS as f32;
+	//Each segment is a thin rect tangent to the circle, just long enough to meet its neighbours.
+	let half_length = radius * (step / 2.0).tan();
+	output_buffer.extend((0..RING_SEGMENTS).map(|n| {
+		let angle = step * n as f32;
+		let mut instance = Instance2D::default();
+		instance.color_tint = tint;
+		instance.texture_coords = text_coords;
+		instance.translate = GLvec2(center.0 + angle.cos() * radius, center.1 + angle.sin() * radius);
+		instance.scale = GLvec2(RING_WIDTH / 2.0, half_length);
+		instance.rotation = GLfloat(angle.to_degrees());
+		instance
+	}));
+}